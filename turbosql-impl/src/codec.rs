@@ -0,0 +1,195 @@
+use once_cell::sync::Lazy;
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote_spanned;
+
+/// How a field's value is converted to and from its SQL column.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Codec {
+	/// The field type's `TurbosqlType` impl if it has one, or JSON in a `TEXT` column otherwise.
+	Native,
+	/// JSON-serialized into a `TEXT` column.
+	Json,
+	/// A timestamp stored as integer milliseconds since the Unix epoch.
+	UnixMs,
+	/// A `Uuid` stored as hyphenated `TEXT` rather than rusqlite's 16-byte `BLOB`.
+	UuidText,
+	/// A `u64` stored as 8 big-endian bytes, from `#[turbosql(u64_as = "blob_be")]`.
+	U64BlobBe,
+	/// A `u64` stored as decimal `TEXT`, from `#[turbosql(u64_as = "text")]`.
+	U64Text,
+}
+
+impl Codec {
+	/// The codec for a column, from its SQL type, stringified Rust type, whether it has `#[turbosql(sql_type = "...")]`, and whether it is a `TEXT` column recorded as storing its values natively.
+	pub(super) fn of(
+		sql_type: &str,
+		rust_type: &str,
+		declared_sql_type: bool,
+		native_text: bool,
+	) -> Codec {
+		let base = base_type(rust_type);
+		match sql_type.split(' ').next().unwrap() {
+			_ if declared_sql_type => Codec::Native,
+			"INTEGER" if datetime_type(rust_type).is_some() => Codec::UnixMs,
+			"BLOB" if base == "u64" => Codec::U64BlobBe,
+			"TEXT" if base == "u64" => Codec::U64Text,
+			// only `String` fields were stored as plain TEXT before other types had native columns, so only recorded columns are
+			"TEXT" if !native_text && !is_string(rust_type) => Codec::Json,
+			"TEXT" if is_uuid(rust_type) => Codec::UuidText,
+			_ => Codec::Native,
+		}
+	}
+
+	/// Suffix of the column alias that tells `select!` how to read a column, as in `SELECT tags AS tags__serialized`.
+	pub(super) fn alias_suffix(self) -> Option<&'static str> {
+		match self {
+			Codec::Native => None,
+			Codec::Json => Some("__serialized"),
+			Codec::UnixMs => Some("__unix_ms"),
+			Codec::UuidText => Some("__uuid_text"),
+			Codec::U64BlobBe => Some("__u64_blob_be"),
+			Codec::U64Text => Some("__u64_text"),
+		}
+	}
+
+	/// The codec and field name of a column alias with an [`alias_suffix`](Codec::alias_suffix).
+	pub(super) fn from_alias(alias: &str) -> (Codec, &str) {
+		[Codec::Json, Codec::UnixMs, Codec::UuidText, Codec::U64BlobBe, Codec::U64Text]
+			.into_iter()
+			.find_map(|codec| Some((codec, alias.strip_suffix(codec.alias_suffix()?)?)))
+			.unwrap_or((Codec::Native, alias))
+	}
+
+	/// Expression converting the field `value` to something that implements `ToSql`, borrowing `value` if needed.
+	pub(super) fn to_sql(self, span: Span, value: TokenStream) -> TokenStream {
+		match self {
+			Codec::Native => quote_spanned!(span => {
+				#[allow(unused_imports)]
+				use ::turbosql::{BindJson as _, BindTyped as _};
+				(&::turbosql::AutoBind(&#value)).turbosql_bind()?
+			}),
+			Codec::Json => quote_spanned!(span => ::turbosql::serde_json::to_string(&#value)?),
+			Codec::UnixMs => quote_spanned!(span => ::turbosql::UnixMs(#value)),
+			Codec::UuidText => quote_spanned!(span => ::turbosql::UuidText(#value)),
+			Codec::U64BlobBe => quote_spanned!(span => ::turbosql::U64BlobBe(#value)),
+			Codec::U64Text => quote_spanned!(span => ::turbosql::U64Text(#value)),
+		}
+	}
+
+	/// Expression converting the field `value` to a `&dyn ToSql` for the current statement.
+	pub(super) fn to_sql_dyn(self, span: Span, value: TokenStream) -> TokenStream {
+		let value = self.to_sql(span, value);
+		quote_spanned!(span => &#value as &dyn ::turbosql::ToSql)
+	}
+
+	/// SQL expression converting `column`'s values from how `previous` stored them to how `self` does, if both store the same value of `rust_type`.
+	pub(super) fn convert_from(
+		self,
+		previous: Codec,
+		rust_type: &str,
+		column: &str,
+	) -> Option<String> {
+		let c = column;
+		// only a JSON string is the text of a native value; anything else, like the array `time` serializes dates as by default, becomes NULL, which the conversion check rejects
+		let text = format!(
+			"CASE WHEN json_valid({c}) THEN CASE json_type({c}) WHEN 'text' THEN json_extract({c}, '$') END ELSE {c} END"
+		);
+		let quoted =
+			|value: &str| format!("CASE WHEN {c} IS NULL THEN NULL ELSE json_quote({value}) END");
+		let hyphenated = format!(
+			"lower(substr(hex({c}), 1, 8) || '-' || substr(hex({c}), 9, 4) || '-' || substr(hex({c}), 13, 4) || '-' || substr(hex({c}), 17, 4) || '-' || substr(hex({c}), 21))"
+		);
+		// a hyphenated UUID that doesn't unhex to 16 bytes becomes NULL, which the rebuild's copy check rejects
+		let bytes =
+			|text: &str| format!("CASE WHEN length({text}) = 36 THEN unhex(replace({text}, '-', '')) END");
+		match (previous, self, is_uuid(rust_type)) {
+			(Codec::Json, Codec::Native, true) => {
+				Some(bytes(&format!("CASE WHEN json_valid({c}) THEN json_extract({c}, '$') END")))
+			}
+			(Codec::UuidText, Codec::Native, true) => Some(bytes(c)),
+			(Codec::Native, Codec::UuidText, true) => Some(hyphenated),
+			(Codec::Native, Codec::Json, true) => Some(quoted(&hyphenated)),
+			(Codec::Json, Codec::Native | Codec::UuidText, _) => Some(text),
+			(Codec::Native | Codec::UuidText, Codec::Json, _) => Some(quoted(c)),
+			_ => None,
+		}
+	}
+
+	/// Expression reading column `i` of `row` into `field` of struct `owner`.
+	pub(super) fn read(self, span: Span, i: usize, owner: &TokenStream, field: &Ident) -> TokenStream {
+		match self {
+			Codec::Native => quote_spanned!(span => {
+				#[allow(unused_imports)]
+				use ::turbosql::{ReadJson as _, ReadTyped as _};
+				(&::turbosql::AutoRead::of(|r: &#owner| &r.#field)).turbosql_read(row, #i)?
+			}),
+			Codec::Json => quote_spanned!(span => {
+				let string: String = row.get(#i)?;
+				::turbosql::serde_json::from_str(&string)?
+			}),
+			Codec::UnixMs => quote_spanned!(span => row.get::<_, ::turbosql::UnixMs<_>>(#i)?.0),
+			Codec::UuidText => quote_spanned!(span => row.get::<_, ::turbosql::UuidText<_>>(#i)?.0),
+			Codec::U64BlobBe => quote_spanned!(span => row.get::<_, ::turbosql::U64BlobBe<_>>(#i)?.0),
+			Codec::U64Text => quote_spanned!(span => row.get::<_, ::turbosql::U64Text<_>>(#i)?.0),
+		}
+	}
+}
+
+/// A date or time type from the `chrono`, `time` or `jiff` crates, which rusqlite stores as ISO-8601 `TEXT`.
+pub(super) struct DateTimeType {
+	/// `DEFAULT` for a `NOT NULL` column of this type.
+	pub(super) text_default: &'static str,
+	/// Whether this type is an instant, which can also be stored as milliseconds since the Unix epoch.
+	pub(super) instant: bool,
+}
+
+static PATH_PREFIX_RE: Lazy<regex::Regex> =
+	Lazy::new(|| regex::Regex::new(r"(?:::\s*)?\b(?:\w+\s*::\s*)+").unwrap());
+
+/// A stringified field type without its paths, e.g. `Option < String >` for `Option < std :: string :: String >`.
+pub(super) fn without_paths(rust_type: &str) -> String {
+	PATH_PREFIX_RE.replace_all(rust_type, "").into_owned()
+}
+
+/// A stringified field type without its `Option` and paths, e.g. `DateTime < Utc >` for `Option < chrono :: DateTime < Utc > >`.
+pub(super) fn base_type(rust_type: &str) -> String {
+	let ty = without_paths(rust_type);
+	ty.strip_prefix("Option < ").and_then(|ty| ty.strip_suffix(" >")).unwrap_or(&ty).to_string()
+}
+
+/// The date or time type of a stringified field type, if its crate's feature is enabled.
+pub(super) fn datetime_type(rust_type: &str) -> Option<DateTimeType> {
+	let (text_default, instant) = match base_type(rust_type).as_str() {
+		"DateTime < Utc >" if cfg!(feature = "chrono") => ("'1970-01-01 00:00:00+00:00'", true),
+		"DateTime < Local >" | "DateTime < FixedOffset >" if cfg!(feature = "chrono") => {
+			("'1970-01-01 00:00:00+00:00'", false)
+		}
+		"NaiveDateTime" if cfg!(feature = "chrono") => ("'1970-01-01T00:00:00'", false),
+		"NaiveDate" if cfg!(feature = "chrono") => ("'1970-01-01'", false),
+		"NaiveTime" if cfg!(feature = "chrono") => ("'00:00:00'", false),
+		"OffsetDateTime" if cfg!(feature = "time") => ("'1970-01-01 00:00:00+00:00'", true),
+		"PrimitiveDateTime" if cfg!(feature = "time") => ("'1970-01-01 00:00:00'", false),
+		"Timestamp" if cfg!(feature = "jiff") => ("'1970-01-01T00:00:00Z'", true),
+		"DateTime" if cfg!(feature = "jiff") => ("'1970-01-01T00:00:00'", false),
+		"Date" if cfg!(any(feature = "time", feature = "jiff")) => ("'1970-01-01'", false),
+		"Time" if cfg!(any(feature = "time", feature = "jiff")) => ("'00:00:00'", false),
+		_ => return None,
+	};
+
+	Some(DateTimeType { text_default, instant })
+}
+
+/// Whether a stringified field type is exactly `String` or `Option < String >`, the only `TEXT` fields that weren't JSON before `native_text` was recorded.
+pub(super) fn is_string(rust_type: &str) -> bool {
+	rust_type == "String" || rust_type == "Option < String >"
+}
+
+/// Whether a stringified field type is a `uuid::Uuid`, with the `uuid` feature enabled.
+pub(super) fn is_uuid(rust_type: &str) -> bool {
+	cfg!(feature = "uuid") && base_type(rust_type) == "Uuid"
+}
+
+/// Whether a stringified field type is a `url::Url`, with the `url` feature enabled.
+pub(super) fn is_url(rust_type: &str) -> bool {
+	cfg!(feature = "url") && base_type(rust_type) == "Url"
+}
@@ -4,17 +4,27 @@ use crate::Table;
 
 pub(super) fn delete(table: &Table) -> proc_macro2::TokenStream {
 	let sql = makesql_delete(table);
-	super::validate_sql_or_abort(&sql);
+	super::validate_sql_or_abort(table.db.as_deref(), &sql);
+
+	let db_handle = table.db_handle();
 
 	quote_spanned! { table.span =>
 		fn delete(&self) -> Result<usize, ::turbosql::Error> {
 			assert!(self.rowid.is_some());
-			::turbosql::__TURBOSQL_DB.with(|db| {
-				let db = db.borrow_mut();
+			#db_handle.with(|db| {
+				let db = db.borrow();
 				let mut stmt = db.prepare_cached(#sql)?;
 				Ok(stmt.execute([self.rowid])?)
 			})
 		}
+
+		fn delete_by_rowid(rowid: i64) -> Result<usize, ::turbosql::Error> {
+			#db_handle.with(|db| {
+				let db = db.borrow();
+				let mut stmt = db.prepare_cached(#sql)?;
+				Ok(stmt.execute([rowid])?)
+			})
+		}
 	}
 }
 
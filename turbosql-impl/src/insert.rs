@@ -1,27 +1,41 @@
-use super::Table;
-use quote::quote_spanned;
+use super::{codec::Codec, Column, Table};
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+
+/// SQLite's default SQLITE_MAX_VARIABLE_NUMBER since 3.32.0
+const SQLITE_MAX_VARIABLE_NUMBER: usize = 32766;
 
 /// INSERT INTO tablename (name1, name2...) VALUES (?1, ?2...)
 pub(super) fn insert(table: &Table) -> proc_macro2::TokenStream {
 	let sql = makesql_insert(table);
 
-	super::validate_sql_or_abort(&sql);
+	super::validate_sql_or_abort(table.db.as_deref(), &sql);
 
-	let columns = table.columns.iter().map(|c| {
-		let ident = &c.ident;
-		if c.sql_type.starts_with("TEXT") && c.rust_type != "Option < String >" && c.rust_type != "String" {
-			quote_spanned!(c.span => &::turbosql::serde_json::to_string(&self.#ident)? as &dyn ::turbosql::ToSql)
-		} else {
-			quote_spanned!(c.span => &self.#ident as &dyn ::turbosql::ToSql)
-		}
-	})
-	.collect::<Vec<_>>();
+	let columns = insert_params(&table.columns, quote!(self));
+	let batch_columns = insert_params_boxed(&table.columns, quote!(row));
+
+	let (sql_batch_prefix, sql_batch_row) = makesql_insert_batch(table);
+
+	super::validate_sql_or_abort(
+		table.db.as_deref(),
+		format!("{}{}, {} RETURNING rowid", sql_batch_prefix, sql_batch_row, sql_batch_row),
+	);
+
+	let rows_per_statement = (SQLITE_MAX_VARIABLE_NUMBER / table.columns.len()).max(1);
+	let column_count = table.columns.len();
+	let sql_max_rowid = format!("SELECT max(rowid) FROM {}", table.name);
+
+	let uuid_v7_idents =
+		table.columns.iter().filter(|c| c.uuid_v7).map(|c| &c.ident).collect::<Vec<_>>();
+
+	let db_handle = table.db_handle();
+	let transaction_fn = table.transaction_fn();
 
 	quote_spanned! { table.span =>
 		fn insert(&self) -> Result<i64, ::turbosql::Error> {
 			assert!(self.rowid.is_none());
-			::turbosql::__TURBOSQL_DB.with(|db| {
-				let db = db.borrow_mut();
+			#db_handle.with(|db| {
+				let db = db.borrow();
 				let mut stmt = db.prepare_cached(#sql)?;
 				Ok(stmt.insert(&[#( #columns ),*] as &[&dyn ::turbosql::ToSql])?)
 			})
@@ -29,8 +43,9 @@ pub(super) fn insert(table: &Table) -> proc_macro2::TokenStream {
 
 		fn insert_mut(&mut self) -> Result<i64, ::turbosql::Error> {
 			assert!(self.rowid.is_none());
-			::turbosql::__TURBOSQL_DB.with(|db| {
-				let db = db.borrow_mut();
+			#( self.#uuid_v7_idents.get_or_insert_with(::turbosql::uuid::Uuid::now_v7); )*
+			#db_handle.with(|db| {
+				let db = db.borrow();
 				let mut stmt = db.prepare_cached(#sql)?;
 				let result = stmt.insert(&[#( #columns ),*] as &[&dyn ::turbosql::ToSql])?;
 				self.rowid = Some(result);
@@ -38,15 +53,71 @@ pub(super) fn insert(table: &Table) -> proc_macro2::TokenStream {
 			})
 		}
 
-		fn insert_batch<T: AsRef<#table>>(rows: &[T]) -> Result<(), ::turbosql::Error> {
-			for row in rows {
-				row.as_ref().insert()?;
-			}
-			Ok(())
+		fn insert_batch<T: AsRef<#table>>(rows: &[T]) -> Result<Vec<i64>, ::turbosql::Error> {
+			#transaction_fn(|| {
+				let mut rowids = Vec::with_capacity(rows.len());
+				for chunk in rows.chunks(#rows_per_statement) {
+					let sql = format!("{}{} RETURNING rowid", #sql_batch_prefix, vec![#sql_batch_row; chunk.len()].join(", "));
+					let mut params: Vec<Box<dyn ::turbosql::ToSql + '_>> = Vec::new();
+					for row in chunk {
+						let row = row.as_ref();
+						assert!(row.rowid.is_none());
+						#( params.push(#batch_columns); )*
+					}
+					#db_handle.with(|db| -> Result<(), ::turbosql::Error> {
+						let db = db.borrow();
+						let max_rowid: Option<i64> = db.prepare_cached(#sql_max_rowid)?.query_row([], |row| row.get(0))?;
+						if max_rowid.unwrap_or(0) > i64::MAX - chunk.len() as i64 {
+							// SQLite picks random unused rowids once i64::MAX is taken, so only one row at a time keeps track of which is which
+							let mut stmt = db.prepare_cached(#sql)?;
+							for row_params in params.chunks(#column_count) {
+								rowids.push(stmt.insert(::turbosql::rusqlite::params_from_iter(row_params))?);
+							}
+							return Ok(());
+						}
+						let mut stmt = db.prepare_cached(&sql)?;
+						let mut chunk_rowids = stmt
+							.query_map(::turbosql::rusqlite::params_from_iter(&params), |row| row.get::<_, i64>(0))?
+							.collect::<Result<Vec<_>, _>>()?;
+						// RETURNING order is unspecified, but below i64::MAX each row gets max(rowid) + 1 in turn, so sorting restores the order of `rows`.
+						chunk_rowids.sort_unstable();
+						rowids.append(&mut chunk_rowids);
+						Ok(())
+					})?;
+				}
+				Ok(rowids)
+			})
 		}
 	}
 }
 
+/// `&dyn ToSql` expressions binding each field of `row` in an `INSERT`, such as `self`.
+pub(super) fn insert_params(columns: &[Column], row: TokenStream) -> Vec<TokenStream> {
+	columns
+		.iter()
+		.map(|c| {
+			let ident = &c.ident;
+			c.codec().to_sql_dyn(c.span, c.insert_value(quote!(#row.#ident)))
+		})
+		.collect()
+}
+
+/// Same as [`insert_params`], as `Box<dyn ToSql>`s that outlive the statement they're collected for.
+pub(super) fn insert_params_boxed(columns: &[Column], row: TokenStream) -> Vec<TokenStream> {
+	columns
+		.iter()
+		.map(|c| {
+			let ident = &c.ident;
+			let value = match c.uuid_v7 && c.codec() == Codec::Native {
+				// a filled-in Option<Uuid> is a temporary, so box it rather than a reference to it
+				true => c.insert_value(quote!(#row.#ident)),
+				false => c.codec().to_sql(c.span, c.insert_value(quote!(#row.#ident))),
+			};
+			quote_spanned!(c.span => Box::new(#value))
+		})
+		.collect()
+}
+
 fn makesql_insert(table: &Table) -> String {
 	let mut sql = format!("INSERT INTO {} (", table.name);
 	sql += table.columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>().join(", ").as_str();
@@ -56,3 +127,14 @@ fn makesql_insert(table: &Table) -> String {
 
 	sql
 }
+
+/// ("INSERT INTO tablename (name1, name2...) VALUES ", "(?, ?...)"), to be joined with one row per record
+fn makesql_insert_batch(table: &Table) -> (String, String) {
+	let mut prefix = format!("INSERT INTO {} (", table.name);
+	prefix += table.columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>().join(", ").as_str();
+	prefix += ") VALUES ";
+
+	let row = format!("({})", table.columns.iter().map(|_| "?").collect::<Vec<_>>().join(", "));
+
+	(prefix, row)
+}
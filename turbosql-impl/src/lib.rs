@@ -4,17 +4,17 @@
 
 #![forbid(unsafe_code)]
 
-const SQLITE_U64_ERROR: &str = r##"SQLite cannot natively store unsigned 64-bit integers, so Turbosql does not support u64 fields. Use i64, u32, f64, or a string or binary format instead. (see https://github.com/trevyn/turbosql/issues/3 )"##;
+const SQLITE_U64_ERROR: &str = r##"SQLite cannot natively store unsigned 64-bit integers. Choose how to store this u64 field with #[turbosql(u64_as = "blob_be")] (8 big-endian bytes, which sort numerically), #[turbosql(u64_as = "checked_i64")] (INTEGER, erroring on values above i64::MAX) or #[turbosql(u64_as = "text")]. (see https://github.com/trevyn/turbosql/issues/3 )"##;
 
 use once_cell::sync::Lazy;
 use proc_macro2::Span;
 use proc_macro_error::{abort, abort_call_site, proc_macro_error};
-use quote::{format_ident, quote, ToTokens};
+use quote::{format_ident, quote, quote_spanned, ToTokens};
 use rusqlite::{params, Connection, Statement};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use syn::{
-	parse::{Parse, ParseStream},
+	parse::{discouraged::Speculative, Parse, ParseStream},
 	punctuated::Punctuated,
 	spanned::Spanned,
 	*,
@@ -25,9 +25,13 @@ const MIGRATIONS_FILENAME: &str = "migrations.toml";
 #[cfg(feature = "test")]
 const MIGRATIONS_FILENAME: &str = "test.migrations.toml";
 
+mod codec;
 mod delete;
 mod insert;
+mod rebuild;
+mod select;
 mod update;
+mod upsert;
 
 #[derive(Debug, Clone)]
 struct Table {
@@ -35,12 +39,57 @@ struct Table {
 	span: Span,
 	name: String,
 	columns: Vec<Column>,
+	unique_keys: Vec<Vec<String>>,
+	/// Non-unique indexes from `#[turbosql(index)]` and `#[turbosql(index(...))]`.
+	indexes: Vec<Vec<String>>,
+	/// Named database from `#[turbosql(db = "...")]`, or `None` for the main database.
+	db: Option<String>,
+	/// Whether `#[turbosql(allow_rebuild)]` permits table-rebuild migrations.
+	allow_rebuild: bool,
+	/// Struct-level `CHECK` expressions from `#[turbosql(check = "...")]` that are table constraints, rather than attached to a column added later.
+	checks: Vec<String>,
+}
+
+impl Table {
+	/// Tokens for this table's database, which has a `.with(|db| ...)` method like the thread-local connection.
+	fn db_handle(&self) -> proc_macro2::TokenStream {
+		db_handle(self.db.as_deref())
+	}
+
+	/// Tokens for the `transaction` function of this table's database.
+	fn transaction_fn(&self) -> proc_macro2::TokenStream {
+		match &self.db {
+			None => quote!(::turbosql::transaction),
+			Some(db) => quote!(::turbosql::db(#db).transaction),
+		}
+	}
+}
+
+fn db_handle(db: Option<&str>) -> proc_macro2::TokenStream {
+	match db {
+		None => quote!(::turbosql::__TURBOSQL_DB),
+		Some(db) => quote!(::turbosql::db(#db)),
+	}
+}
+
+/// Named databases become part of file names, so keep them simple.
+fn validate_db_name(name: &LitStr) -> String {
+	let value = name.value();
+	if value.is_empty() || !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+		abort!(
+			name,
+			"Database name must be non-empty and contain only ASCII letters, digits and underscores"
+		);
+	}
+	value
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 struct MiniTable {
 	name: String,
 	columns: Vec<MiniColumn>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	checks: Vec<String>,
 }
 
 impl ToTokens for Table {
@@ -58,6 +107,62 @@ struct Column {
 	rust_type: String,
 	sql_type: &'static str,
 	sql_default: Option<String>,
+	unique: bool,
+	index: bool,
+	/// Previous name of this column, from `#[turbosql(renamed_from = "...")]`.
+	renamed_from: Option<String>,
+	/// Foreign key target and action, e.g. `person(rowid) ON DELETE CASCADE`, from `Option<Ref<T>>` or `#[turbosql(references = T)]`.
+	references: Option<String>,
+	/// `CHECK` expressions from `#[turbosql(check = "...")]` on this field, and struct-level checks attached to it.
+	checks: Vec<String>,
+	/// Whether the column type comes from `#[turbosql(sql_type = "...")]` or `#[turbosql(enum_values(...))]` rather than the field's type name.
+	declared_sql_type: bool,
+	/// Whether a `TEXT` column other than a plain `String` stores its values natively rather than as JSON, as every such column did before this was recorded.
+	native_text: bool,
+	/// Whether inserts fill in a missing value with a new UUIDv7, from `#[turbosql(uuid_v7)]`.
+	uuid_v7: bool,
+	/// SQL literals from `#[turbosql(enum_values(...))]`, checked against the field's `TurbosqlEnum`.
+	enum_values: Vec<String>,
+}
+
+impl Column {
+	/// Type and constraints of this column of `table`, as used in `CREATE TABLE` and `ADD COLUMN`.
+	fn sql_definition(&self, table: &str) -> String {
+		sql_definition(table, self.sql_type, &self.references, &self.checks)
+	}
+
+	fn codec(&self) -> codec::Codec {
+		codec::Codec::of(self.sql_type, &self.rust_type, self.declared_sql_type, self.native_text)
+	}
+
+	/// Expression for this field's `value` in an `INSERT`, with a new UUIDv7 in place of a missing `uuid_v7` value.
+	fn insert_value(&self, value: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+		match self.uuid_v7 {
+			true => quote_spanned!(self.span => #value.or_else(|| Some(::turbosql::uuid::Uuid::now_v7()))),
+			false => value,
+		}
+	}
+}
+
+fn sql_definition(
+	table: &str,
+	sql_type: &str,
+	references: &Option<String>,
+	checks: &[String],
+) -> String {
+	let mut sql = sql_type.to_string();
+	if let Some(references) = references {
+		sql += &format!(" REFERENCES {}", references);
+	}
+	for check in checks {
+		sql += &format!(" {}", check_constraint(table, check));
+	}
+	sql
+}
+
+/// A `CHECK` constraint of `table`, named `table: check` so that a violation, which SQLite reports by constraint name, identifies both.
+fn check_constraint(table: &str, check: &str) -> String {
+	format!("CONSTRAINT \"{}: {}\" CHECK ({})", table, check.replace('"', "\"\""), check)
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -65,23 +170,79 @@ struct MiniColumn {
 	name: String,
 	rust_type: String,
 	sql_type: String,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	references: Option<String>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	checks: Vec<String>,
+	#[serde(default, skip_serializing_if = "std::ops::Not::not")]
+	declared_sql_type: bool,
+	#[serde(default, skip_serializing_if = "std::ops::Not::not")]
+	native_text: bool,
+}
+
+impl MiniColumn {
+	/// Same as [`Column::sql_definition`], for a column from the last compile.
+	fn sql_definition(&self, table: &str) -> String {
+		sql_definition(table, &self.sql_type, &self.references, &self.checks)
+	}
+
+	fn codec(&self) -> codec::Codec {
+		codec::Codec::of(&self.sql_type, &self.rust_type, self.declared_sql_type, self.native_text)
+	}
 }
 
 static OPTION_U8_ARRAY_RE: Lazy<regex::Regex> =
 	Lazy::new(|| regex::Regex::new(r"^Option\s*<\s*\[\s*u8\s*;\s*\d+\s*\]\s*>$").unwrap());
 static U8_ARRAY_RE: Lazy<regex::Regex> =
 	Lazy::new(|| regex::Regex::new(r"^\[\s*u8\s*;\s*\d+\s*\]$").unwrap());
+static OPTION_REF_RE: Lazy<regex::Regex> = Lazy::new(|| {
+	regex::Regex::new(
+		r"^Option\s*<\s*(?:(?:::\s*)?turbosql\s*::\s*)?Ref\s*<\s*(?:\w+\s*::\s*)*(\w+)\s*>\s*>$",
+	)
+	.unwrap()
+});
+static REF_RE: Lazy<regex::Regex> =
+	Lazy::new(|| regex::Regex::new(r"^(?:(?:::\s*)?turbosql\s*::\s*)?Ref\s*<.*>$").unwrap());
 
 #[derive(Clone, Debug)]
 struct SingleColumn {
 	table: Ident,
 	column: Ident,
+	/// The column from `output_generated_tables_do_not_edit`, set by [`resolve`](SingleColumn::resolve).
+	mini_column: Option<MiniColumn>,
+}
+
+impl SingleColumn {
+	/// Look up this column in `output_generated_tables_do_not_edit` of the database `db`.
+	fn resolve(&mut self, migrations_toml: &MigrationsToml, db: Option<&str>) {
+		let table_name = self.table.to_string().to_lowercase();
+
+		let Some(table) = migrations_toml.table(db, &table_name) else {
+			abort!(
+				self.table,
+				"Table {:?} not found. Does struct {} exist and have #[derive(Turbosql, Default)]?{}",
+				table_name,
+				self.table,
+				migrations_toml.named_db_hint(db, &table_name)
+			);
+		};
+
+		match table.columns.iter().find(|c| self.column == c.name) {
+			Some(c) => self.mini_column = Some(c.clone()),
+			None => {
+				abort!(self.column, "Column {:?} not found in table {:?}", self.column.to_string(), table_name)
+			}
+		}
+	}
+
+	fn mini_column(&self) -> &MiniColumn {
+		self.mini_column.as_ref().expect("SingleColumn is resolved right after parsing")
+	}
 }
 
 #[derive(Clone, Debug)]
 enum Content {
 	Type(Type),
-	#[allow(dead_code)]
 	SingleColumn(SingleColumn),
 }
 
@@ -89,7 +250,7 @@ impl ToTokens for Content {
 	fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
 		match self {
 			Content::Type(ty) => ty.to_tokens(tokens),
-			Content::SingleColumn(_) => unimplemented!(),
+			Content::SingleColumn(_) => self.ty().unwrap().to_tokens(tokens),
 		}
 	}
 }
@@ -98,17 +259,16 @@ impl Content {
 	fn ty(&self) -> Result<Type> {
 		match self {
 			Content::Type(ty) => Ok(ty.clone()),
-			Content::SingleColumn(_) => unimplemented!(), // parse_str(&c.rust_type),
+			Content::SingleColumn(c) => parse_str(&c.mini_column().rust_type),
 		}
 	}
-	fn is_primitive(&self) -> bool {
+	/// Whether this is a `#[derive(Turbosql)]` struct, whose rows are read field by field even if only one column is selected.
+	fn is_table(&self, migrations_toml: &MigrationsToml, db: Option<&str>) -> bool {
 		match self {
-			Content::Type(Type::Path(TypePath { path, .. })) => {
-				["f32", "f64", "i8", "u8", "i16", "u16", "i32", "u32", "i64", "String", "bool", "Blob"]
-					.contains(&path.segments.last().unwrap().ident.to_string().as_str())
-			}
-			Content::Type(Type::Array(_)) => true,
-			_ => abort_call_site!("Unsupported content type in is_primitive {:#?}", self),
+			Content::Type(Type::Path(TypePath { path, .. })) => migrations_toml
+				.table(db, &path.segments.last().unwrap().ident.to_string().to_lowercase())
+				.is_some(),
+			_ => false,
 		}
 	}
 	fn table_ident(&self) -> &Ident {
@@ -136,14 +296,52 @@ impl ResultType {
 	}
 }
 
+impl ResultType {
+	/// Parse the `Table.column`, `Vec<Table.column>` and `Option<Table.column>` forms.
+	fn parse_single_column(input: ParseStream) -> Result<Self> {
+		let container = match input.peek(Ident) && input.peek2(Token![<]) {
+			true => {
+				let ident = input.parse::<Ident>()?;
+				if !["Vec", "Option", "Iter"].contains(&ident.to_string().as_str()) {
+					return Err(Error::new(ident.span(), "Expected Vec, Option or Iter"));
+				}
+				input.parse::<Token![<]>()?;
+				Some(ident)
+			}
+			false => None,
+		};
+
+		let table = input.call(Path::parse_mod_style)?;
+		input.parse::<Token![.]>()?;
+		let column = input.parse::<Ident>()?;
+
+		if container.is_some() {
+			input.parse::<Token![>]>()?;
+		}
+
+		let table = table.segments.last().unwrap().ident.clone();
+
+		Ok(ResultType {
+			container,
+			content: Content::SingleColumn(SingleColumn { table, column, mini_column: None }),
+		})
+	}
+}
+
 impl Parse for ResultType {
 	fn parse(input: ParseStream) -> Result<Self> {
+		let fork = input.fork();
+		if let Ok(result_type) = ResultType::parse_single_column(&fork) {
+			input.advance_to(&fork);
+			return Ok(result_type);
+		}
+
 		input.parse::<Type>().map(|ty| match ty {
 			Type::Path(TypePath { qself: None, ref path }) => {
 				let path = path.segments.last().unwrap();
 				let mut container = None;
 				let content = match &path.ident {
-					ident if ["Vec", "Option"].contains(&ident.to_string().as_str()) => {
+					ident if ["Vec", "Option", "Iter"].contains(&ident.to_string().as_str()) => {
 						container = Some(ident.clone());
 						match path.arguments {
 							PathArguments::AngleBracketed(AngleBracketedGenericArguments { ref args, .. }) => {
@@ -161,6 +359,7 @@ impl Parse for ResultType {
 				ResultType { container, content }
 			}
 			Type::Array(array) => ResultType { container: None, content: Content::Type(Type::Array(array)) },
+			Type::Tuple(tuple) => ResultType { container: None, content: Content::Type(Type::Tuple(tuple)) },
 			ty => abort!(ty, "Unknown type {:?}", ty),
 		})
 	}
@@ -174,21 +373,20 @@ struct MembersAndCasters {
 }
 
 impl MembersAndCasters {
-	fn create(members: Vec<(Ident, Ident, usize)>) -> MembersAndCasters {
+	/// Casters reading each selected column into the field of struct `owner` that it's named after.
+	fn create(
+		members: Vec<(Ident, Ident, usize)>,
+		owner: &proc_macro2::TokenStream,
+	) -> MembersAndCasters {
 		// let struct_members: Vec<_> = members.iter().map(|(name, ty, _i)| quote!(#name: #ty)).collect();
 		let row_casters = members
 			.iter()
 			.map(|(name, _ty, i)| {
-				if name.to_string().ends_with("__serialized") {
-					let name = name.to_string();
-					let real_name = format_ident!("{}", name.strip_suffix("__serialized").unwrap());
-					quote!(#real_name: {
-						let string: String = row.get(#i)?;
-						::turbosql::serde_json::from_str(&string)?
-					})
-				} else {
-					quote!(#name: row.get(#i)?)
-				}
+				let name = name.to_string();
+				let (codec, real_name) = codec::Codec::from_alias(&name);
+				let real_name = format_ident!("{}", real_name);
+				let value = codec.read(Span::call_site(), *i, owner, &real_name);
+				quote!(#real_name: #value)
 			})
 			.collect::<Vec<_>>();
 
@@ -220,7 +418,11 @@ fn _extract_explicit_members(columns: &[String]) -> Option<MembersAndCasters> {
 	None
 }
 
-fn _extract_stmt_members(stmt: &Statement, span: &Span) -> MembersAndCasters {
+fn _extract_stmt_members(
+	stmt: &Statement,
+	span: &Span,
+	owner: &proc_macro2::TokenStream,
+) -> MembersAndCasters {
 	let members: Vec<_> = stmt
 		.column_names()
 		.iter()
@@ -255,7 +457,7 @@ fn _extract_stmt_members(stmt: &Statement, span: &Span) -> MembersAndCasters {
 	// let row_casters: Vec<_> =
 	//  members.iter().map(|(name, _ty, i)| quote!(#name: row.get(#i).unwrap())).collect();
 
-	MembersAndCasters::create(members)
+	MembersAndCasters::create(members, owner)
 }
 
 const SELECT: usize = 1;
@@ -266,11 +468,13 @@ const UPDATE: usize = 3;
 struct StatementInfo {
 	positional_parameter_count: usize,
 	named_parameters: Vec<String>,
+	/// Name of each parameter in SQLite index order, `None` for positional `?` parameters.
+	parameter_names: Vec<Option<String>>,
 	column_names: Vec<String>,
 }
 
 impl StatementInfo {
-	fn membersandcasters(&self) -> Result<MembersAndCasters> {
+	fn membersandcasters(&self, owner: &proc_macro2::TokenStream) -> Result<MembersAndCasters> {
 		Ok(MembersAndCasters::create(
 			self
 				.column_names
@@ -278,6 +482,7 @@ impl StatementInfo {
 				.enumerate()
 				.map(|(i, col_name)| Ok((parse_str::<Ident>(col_name)?, format_ident!("None"), i)))
 				.collect::<Result<Vec<_>>>()?,
+			owner,
 		))
 	}
 }
@@ -287,6 +492,49 @@ struct MigrationsToml {
 	migrations_append_only: Option<Vec<String>>,
 	output_generated_schema_for_your_information_do_not_edit: Option<String>,
 	output_generated_tables_do_not_edit: Option<BTreeMap<String, MiniTable>>,
+	databases: Option<BTreeMap<String, MigrationsToml>>,
+}
+
+impl MigrationsToml {
+	/// The `[databases.name]` section for a named database, or the top level for the main database.
+	fn for_db(self, db: Option<&str>) -> MigrationsToml {
+		match db {
+			None => self,
+			Some(name) => self.databases.and_then(|mut dbs| dbs.remove(name)).unwrap_or_default(),
+		}
+	}
+
+	/// Same as [`for_db`](MigrationsToml::for_db), by reference. `None` for an unknown named database.
+	fn for_db_ref(&self, db: Option<&str>) -> Option<&MigrationsToml> {
+		match db {
+			None => Some(self),
+			Some(name) => self.databases.as_ref()?.get(name),
+		}
+	}
+
+	/// A table from `output_generated_tables_do_not_edit` of the database `db`.
+	fn table(&self, db: Option<&str>, table_name: &str) -> Option<&MiniTable> {
+		self.for_db_ref(db)?.output_generated_tables_do_not_edit.as_ref()?.get(table_name)
+	}
+
+	/// If a table missing from the main database is in a named one, suggest selecting it.
+	fn named_db_hint(&self, db: Option<&str>, table_name: &str) -> String {
+		if db.is_some() {
+			return String::new();
+		}
+
+		self
+			.databases
+			.iter()
+			.flatten()
+			.find(|(_, toml)| {
+				toml.output_generated_tables_do_not_edit.as_ref().is_some_and(|t| t.contains_key(table_name))
+			})
+			.map(|(name, _)| {
+				format!(" It is in database {:?}; select it with `db = {:?},` first.", name, name)
+			})
+			.unwrap_or_default()
+	}
 }
 
 fn migrations_to_tempdb(migrations: &[String]) -> Connection {
@@ -314,7 +562,7 @@ fn migrations_to_tempdb(migrations: &[String]) -> Connection {
 fn migrations_to_schema(migrations: &[String]) -> rusqlite::Result<String> {
 	Ok(
 		migrations_to_tempdb(migrations)
-			.prepare("SELECT sql FROM sqlite_master WHERE type='table' ORDER BY sql")?
+			.prepare("SELECT sql FROM sqlite_master WHERE type IN ('table', 'index') AND sql IS NOT NULL ORDER BY sql")?
 			.query_map(params![], |row| row.get(0))?
 			.collect::<rusqlite::Result<Vec<String>>>()?
 			.join("\n"),
@@ -343,33 +591,71 @@ fn read_migrations_toml() -> MigrationsToml {
 	}
 }
 
-fn validate_sql<S: AsRef<str>>(sql: S) -> rusqlite::Result<StatementInfo> {
-	let tempdb = migrations_to_tempdb(&read_migrations_toml().migrations_append_only.unwrap());
+fn validate_sql<S: AsRef<str>>(
+	migrations_toml: &MigrationsToml,
+	db: Option<&str>,
+	sql: S,
+) -> rusqlite::Result<StatementInfo> {
+	let migrations = migrations_toml.for_db_ref(db).and_then(|t| t.migrations_append_only.as_deref());
+	let tempdb = migrations_to_tempdb(migrations.unwrap_or_default());
 
 	let stmt = tempdb.prepare(sql.as_ref())?;
 	let mut positional_parameter_count = stmt.parameter_count();
 	let mut named_parameters = Vec::new();
+	let mut parameter_names = Vec::new();
 
 	for idx in 1..=stmt.parameter_count() {
-		if let Some(parameter_name) = stmt.parameter_name(idx) {
-			named_parameters.push(parameter_name.to_string());
+		let parameter_name = stmt.parameter_name(idx).map(str::to_string);
+		if let Some(parameter_name) = &parameter_name {
+			named_parameters.push(parameter_name.clone());
 			positional_parameter_count -= 1;
 		}
+		parameter_names.push(parameter_name);
 	}
 
 	Ok(StatementInfo {
 		positional_parameter_count,
 		named_parameters,
+		parameter_names,
 		column_names: stmt.column_names().into_iter().map(str::to_string).collect(),
 	})
 }
 
-fn validate_sql_or_abort<S: AsRef<str> + std::fmt::Debug>(sql: S) -> StatementInfo {
-	validate_sql(sql.as_ref()).unwrap_or_else(|e| {
+fn validate_sql_or_abort<S: AsRef<str> + std::fmt::Debug>(
+	db: Option<&str>,
+	sql: S,
+) -> StatementInfo {
+	validate_sql_or_abort_in(&read_migrations_toml(), db, sql)
+}
+
+/// Same as [`validate_sql_or_abort`], against an already read `migrations.toml`.
+fn validate_sql_or_abort_in<S: AsRef<str> + std::fmt::Debug>(
+	migrations_toml: &MigrationsToml,
+	db: Option<&str>,
+	sql: S,
+) -> StatementInfo {
+	validate_sql(migrations_toml, db, sql.as_ref()).unwrap_or_else(|e| {
 		abort_call_site!(r#"Error validating SQL statement: "{}". SQL: {:?}"#, e, sql)
 	})
 }
 
+/// Parse an optional leading `db = "name",` selecting a named database.
+fn parse_db_selector(input: ParseStream) -> Result<Option<String>> {
+	if !(input.peek(Ident) && input.peek2(Token![=])) {
+		return Ok(None);
+	}
+
+	let ident = input.parse::<Ident>()?;
+	if ident != "db" {
+		return Err(Error::new(ident.span(), "Expected `db = \"name\",` or a result type"));
+	}
+	input.parse::<Token![=]>()?;
+	let name = validate_db_name(&input.parse::<LitStr>()?);
+	input.parse::<Token![,]>()?;
+
+	Ok(Some(name))
+}
+
 fn parse_interpolated_sql(
 	input: ParseStream,
 ) -> Result<(Option<String>, Punctuated<Expr, Token![,]>, proc_macro2::TokenStream)> {
@@ -412,19 +698,27 @@ fn parse_interpolated_sql(
 }
 
 fn do_parse_tokens<const T: usize>(input: ParseStream) -> Result<proc_macro2::TokenStream> {
+	let db = parse_db_selector(input)?;
+	let db = db.as_deref();
 	let span = input.span();
-	let result_type = input.parse::<ResultType>().ok();
+	let mut result_type = input.parse::<ResultType>().ok();
+	// read once, since validating and looking up tables would otherwise each read it again
+	let migrations_toml = read_migrations_toml();
+	if let Some(ResultType { content: Content::SingleColumn(col), .. }) = &mut result_type {
+		col.resolve(&migrations_toml, db);
+	}
+	let sql_span = input.span();
 	let (mut sql, params, sql_and_parameters_tokens) = parse_interpolated_sql(input)?;
 
 	// Try validating SQL as-is
 
-	let mut stmt_info = sql.as_ref().and_then(|s| validate_sql(s).ok());
+	let mut stmt_info = sql.as_ref().and_then(|s| validate_sql(&migrations_toml, db, s).ok());
 
 	// Try adding SELECT or UPDATE if it didn't validate
 
 	if let (true, Some(orig_sql), None) = (T == SELECT || T == UPDATE, &sql, &stmt_info) {
 		let sql_modified = format!("{} {}", if T == SELECT { "SELECT" } else { "UPDATE" }, orig_sql);
-		if let Ok(stmt_info_modified) = validate_sql(&sql_modified) {
+		if let Ok(stmt_info_modified) = validate_sql(&migrations_toml, db, &sql_modified) {
 			sql = Some(sql_modified);
 			stmt_info = Some(stmt_info_modified);
 		}
@@ -434,8 +728,16 @@ fn do_parse_tokens<const T: usize>(input: ParseStream) -> Result<proc_macro2::To
 
 	if is_rust_analyzer() {
 		return Ok(if let Some(ty) = result_type {
-			let ty = ty.ty()?;
-			quote!(Ok({let x: #ty = Default::default(); x}))
+			match ty.container {
+				Some(ref container) if container == "Iter" => {
+					let content = ty.content.ty()?;
+					quote!(::turbosql::SelectIter::<#content, _>::new(|_| Ok(())))
+				}
+				_ => {
+					let ty = ty.ty()?;
+					quote!(Ok({let x: #ty = Default::default(); x}))
+				}
+			}
 		} else {
 			quote!()
 		});
@@ -445,28 +747,38 @@ fn do_parse_tokens<const T: usize>(input: ParseStream) -> Result<proc_macro2::To
 
 	let (sql, stmt_info) = match (result_type.clone(), sql, stmt_info) {
 		//
+		// Tuples have no table to generate SELECT ... FROM from, so report why the SQL did not validate
+		(Some(ResultType { content: Content::Type(Type::Tuple(_)), .. }), sql, None) => {
+			let Some(sql) = sql else {
+				abort_call_site!(
+					r#"Tuple result types need SQL with a column list, e.g. select!((String, i64) "name, age FROM person")"#
+				);
+			};
+			let sql = match sql.trim_start().to_uppercase().starts_with("SELECT") {
+				true => sql,
+				false => format!("SELECT {}", sql),
+			};
+			validate_sql_or_abort_in(&migrations_toml, db, &sql);
+			// migrations.toml changed between the two attempts, e.g. another macro added a table meanwhile
+			abort_call_site!(
+				"SQL statement {:?} only validated on a second attempt, probably because migrations.toml changed while compiling. Try recompiling.",
+				sql
+			)
+		}
+
 		// Have result type and SQL did not validate, try generating SELECT ... FROM
 		(Some(ResultType { content, .. }), sql, None) => {
 			let table_type = content.table_ident().to_string();
 			let table_name = table_type.to_lowercase();
 
-			let table = {
-				let t = match read_migrations_toml().output_generated_tables_do_not_edit {
-					Some(m) => m.get(&table_name).cloned(),
-					None => None,
-				};
-
-				match t {
-					Some(t) => t,
-					None => {
-						abort!(
-							span,
-							"Table {:?} not found. Does struct {} exist and have #[derive(Turbosql, Default)]?",
-							table_name,
-							table_type
-						);
-					}
-				}
+			let Some(table) = migrations_toml.table(db, &table_name) else {
+				abort!(
+					span,
+					"Table {:?} not found. Does struct {} exist and have #[derive(Turbosql, Default)]?{}",
+					table_name,
+					table_type,
+					migrations_toml.named_db_hint(db, &table_name)
+				);
 			};
 
 			let column_names_str = table
@@ -477,13 +789,9 @@ fn do_parse_tokens<const T: usize>(input: ParseStream) -> Result<proc_macro2::To
 						Content::SingleColumn(col) => col.column == c.name,
 						_ => true,
 					} {
-						if c.sql_type.starts_with("TEXT")
-							&& c.rust_type != "Option < String >"
-							&& c.rust_type != "String"
-						{
-							Some(format!("{} AS {}__serialized", c.name, c.name))
-						} else {
-							Some(c.name.clone())
+						match c.codec().alias_suffix() {
+							Some(suffix) => Some(format!("{} AS {}{}", c.name, c.name, suffix)),
+							None => Some(c.name.clone()),
 						}
 					} else {
 						None
@@ -494,7 +802,7 @@ fn do_parse_tokens<const T: usize>(input: ParseStream) -> Result<proc_macro2::To
 
 			let sql = format!("SELECT {} FROM {} {}", column_names_str, table_name, sql.unwrap_or_default());
 
-			(sql.clone(), validate_sql_or_abort(sql))
+			(sql.clone(), validate_sql_or_abort_in(&migrations_toml, db, sql))
 		}
 
 		// Otherwise, everything is validated, just unwrap
@@ -503,8 +811,11 @@ fn do_parse_tokens<const T: usize>(input: ParseStream) -> Result<proc_macro2::To
 		_ => abort_call_site!("no predicate and no result type found"),
 	};
 
-	if !stmt_info.named_parameters.is_empty() {
-		abort_call_site!("SQLite named parameters not currently supported.");
+	if let Some(p) = stmt_info.named_parameters.iter().find(|p| p.starts_with('?')) {
+		abort_call_site!(
+			"SQLite numbered parameters such as {} are not supported; use ? or $name instead.",
+			p
+		);
 	}
 
 	if params.len() != stmt_info.positional_parameter_count {
@@ -522,14 +833,29 @@ fn do_parse_tokens<const T: usize>(input: ParseStream) -> Result<proc_macro2::To
 		return Err(input.error("Expected parameters"));
 	}
 
+	let db_handle = db_handle(db);
+
 	let params = if stmt_info.named_parameters.is_empty() {
 		quote! { ::turbosql::params![#params] }
 	} else {
-		let param_quotes = stmt_info.named_parameters.iter().map(|p| {
-			let var_ident = format_ident!("{}", &p[1..]);
-			quote!(#p: &#var_ident,)
+		// Named parameters ($name, :name, @name) bind the in-scope Rust variable of the same name.
+		// Bind everything in SQLite index order, so named and positional parameters can be mixed.
+		let mut positional = params.iter();
+		let param_quotes = stmt_info.parameter_names.iter().map(|p| match p {
+			Some(p) => {
+				let mut var_ident = parse_str::<Ident>(&p[1..]).unwrap_or_else(|_| {
+					abort_call_site!("SQLite named parameter {} is not a valid Rust identifier", p)
+				});
+				// Resolve the variable where the SQL string was written, even if the call is wrapped in another macro.
+				var_ident.set_span(sql_span);
+				quote!(&#var_ident as &dyn ::turbosql::ToSql)
+			}
+			None => {
+				let param = positional.next().unwrap();
+				quote!(&#param as &dyn ::turbosql::ToSql)
+			}
 		});
-		quote! { ::turbosql::named_params![#(#param_quotes),*] }
+		quote! { &[#(#param_quotes),*] as &[&dyn ::turbosql::ToSql] }
 	};
 
 	// if we return no columns, this should be an execute or update
@@ -542,8 +868,8 @@ fn do_parse_tokens<const T: usize>(input: ParseStream) -> Result<proc_macro2::To
 		return Ok(quote! {
 		{
 			(|| -> std::result::Result<usize, ::turbosql::Error> {
-				::turbosql::__TURBOSQL_DB.with(|db| {
-					let db = db.borrow_mut();
+				#db_handle.with(|db| {
+					let db = db.borrow();
 					let mut stmt = db.prepare_cached(#sql)?;
 					Ok(stmt.execute(#params)?)
 				})
@@ -565,12 +891,31 @@ fn do_parse_tokens<const T: usize>(input: ParseStream) -> Result<proc_macro2::To
 	let handle_row;
 	let content_ty;
 
-	if content.is_primitive() {
+	if let Content::SingleColumn(col) = &content {
+		let table = &col.table;
+		handle_row = col.mini_column().codec().read(Span::call_site(), 0, &quote!(#table), &col.column);
+		content_ty = quote! { #content };
+	} else if let Content::Type(Type::Tuple(tuple)) = &content {
+		if tuple.elems.len() != stmt_info.column_names.len() {
+			abort!(
+				tuple,
+				"Tuple has {} element{}, but SQL returns {} column{}: {:?}",
+				tuple.elems.len(),
+				if tuple.elems.len() == 1 { "" } else { "s" },
+				stmt_info.column_names.len(),
+				if stmt_info.column_names.len() == 1 { "" } else { "s" },
+				sql
+			);
+		}
+		let getters = (0..tuple.elems.len()).map(|i| quote!(row.get(#i)?));
+		handle_row = quote! { (#(#getters,)*) };
+		content_ty = quote! { #content };
+	} else if stmt_info.column_names.len() == 1 && !content.is_table(&migrations_toml, db) {
 		handle_row = quote! { row.get(0)? };
 		content_ty = quote! { #content };
 	} else {
 		let MembersAndCasters { row_casters } = stmt_info
-			.membersandcasters()
+			.membersandcasters(&quote!(#content))
 			.unwrap_or_else(|_| abort_call_site!("stmt_info.membersandcasters failed"));
 
 		handle_row = quote! {
@@ -583,42 +928,42 @@ fn do_parse_tokens<const T: usize>(input: ParseStream) -> Result<proc_macro2::To
 		content_ty = quote! { #content };
 	}
 
-	// Content::SingleColumn(col) => {
-	// 	handle_row = quote! { row.get(0)? };
-	// 	let rust_ty: Type = parse_str(
-	// 		&read_migrations_toml()
-	// 			.output_generated_tables_do_not_edit
-	// 			.unwrap()
-	// 			.get(&col.table.to_string().to_lowercase())
-	// 			.unwrap()
-	// 			.columns
-	// 			.iter()
-	// 			.find(|c| col.column == c.name)
-	// 			.unwrap()
-	// 			.rust_type,
-	// 	)?;
-	// 	content_ty = quote! { #rust_ty };
-	// }
-	// };
-
 	// Decide how to handle the iterator over rows depending on container.
 
 	let return_type;
 	let handle_result;
 
 	match container {
+		Some(ident) if ident == "Iter" => {
+			// Rows are streamed from the statement into a callback, so the query runs when the
+			// returned SelectIter is driven rather than here.
+			return Ok(quote! {
+				::turbosql::SelectIter::<#content_ty, _>::new(|f| {
+					#db_handle.with(|db| {
+						let db = db.borrow();
+						let mut stmt = db.prepare_cached(#sql)?;
+						let mut result = stmt.query_and_then(#params, |row| -> std::result::Result<#content_ty, ::turbosql::Error> {
+							Ok(#handle_row)
+						})?;
+						f(&mut result);
+						Ok(())
+					})
+				})
+			});
+		}
 		Some(ident) if ident == "Vec" => {
 			return_type = quote! { Vec<#content_ty> };
-			handle_result = quote! { result.collect::<Vec<_>>() };
+			handle_result = quote! { result.collect() };
 		}
 		Some(ident) if ident == "Option" => {
 			return_type = quote! { Option<#content_ty> };
-			handle_result = quote! { result.next() };
+			handle_result = quote! { result.next().transpose() };
 		}
 		None => {
 			return_type = quote! { #content_ty };
-			handle_result =
-				quote! { result.next().ok_or(::turbosql::rusqlite::Error::QueryReturnedNoRows)? };
+			handle_result = quote! {
+				result.next().unwrap_or_else(|| Err(::turbosql::rusqlite::Error::QueryReturnedNoRows.into()))
+			};
 		}
 		_ => unreachable!("No other container type is possible"),
 	}
@@ -628,13 +973,13 @@ fn do_parse_tokens<const T: usize>(input: ParseStream) -> Result<proc_macro2::To
 	Ok(quote! {
 		{
 			(|| -> std::result::Result<#return_type, ::turbosql::Error> {
-				::turbosql::__TURBOSQL_DB.with(|db| {
-					let db = db.borrow_mut();
+				#db_handle.with(|db| {
+					let db = db.borrow();
 					let mut stmt = db.prepare_cached(#sql)?;
 					let mut result = stmt.query_and_then(#params, |row| -> std::result::Result<#content_ty, ::turbosql::Error> {
 						Ok(#handle_row)
-					})?.flatten();
-					Ok(#handle_result)
+					})?;
+					#handle_result
 				})
 			})()
 		}
@@ -675,10 +1020,17 @@ pub fn turbosql_derive_macro(input: proc_macro::TokenStream) -> proc_macro::Toke
 		impl ::turbosql::Turbosql for #table_ident {
 			fn insert(&self) -> Result<i64, ::turbosql::Error> { unimplemented!() }
 			fn insert_mut(&mut self) -> Result<i64, ::turbosql::Error> { unimplemented!() }
-			fn insert_batch<T: AsRef<Self>>(rows: &[T]) -> Result<(), ::turbosql::Error> { unimplemented!() }
+			fn insert_batch<T: AsRef<Self>>(rows: &[T]) -> Result<Vec<i64>, ::turbosql::Error> { unimplemented!() }
 			fn update(&self) -> Result<usize, ::turbosql::Error> { unimplemented!() }
-			fn update_batch<T: AsRef<Self>>(rows: &[T]) -> Result<(), ::turbosql::Error> { unimplemented!() }
+			fn update_batch<T: AsRef<Self>>(rows: &[T]) -> Result<usize, ::turbosql::Error> { unimplemented!() }
+			fn upsert(&self) -> Result<i64, ::turbosql::Error> { unimplemented!() }
+			fn upsert_batch<T: AsRef<Self>>(rows: &[T]) -> Result<Vec<i64>, ::turbosql::Error> { unimplemented!() }
 			fn delete(&self) -> Result<usize, ::turbosql::Error> { unimplemented!() }
+			fn delete_by_rowid(rowid: i64) -> Result<usize, ::turbosql::Error> { unimplemented!() }
+			fn get(rowid: i64) -> Result<Option<Self>, ::turbosql::Error> { unimplemented!() }
+			fn all() -> Result<Vec<Self>, ::turbosql::Error> { unimplemented!() }
+			fn count() -> Result<i64, ::turbosql::Error> { unimplemented!() }
+			fn exists(rowid: i64) -> Result<bool, ::turbosql::Error> { unimplemented!() }
 		}
 	};
 
@@ -692,11 +1044,32 @@ pub fn turbosql_derive_macro(input: proc_macro::TokenStream) -> proc_macro::Toke
 		abort_call_site!("The Turbosql derive macro only supports structs with named fields");
 	};
 
+	let mut columns = extract_columns(fields);
+	let has_skipped_fields = columns.len() != fields.named.len();
+	let table_attrs = extract_table_attrs(&input.attrs, &columns);
+	let checks =
+		place_struct_checks(&table_name, table_attrs.db.as_deref(), &mut columns, table_attrs.checks);
+
 	let table = Table {
 		ident: table_ident,
 		span: table_span,
 		name: table_name.clone(),
-		columns: extract_columns(fields),
+		unique_keys: columns
+			.iter()
+			.filter(|c| c.unique)
+			.map(|c| vec![c.name.clone()])
+			.chain(table_attrs.unique_keys)
+			.collect(),
+		indexes: columns
+			.iter()
+			.filter(|c| c.index)
+			.map(|c| vec![c.name.clone()])
+			.chain(table_attrs.indexes)
+			.collect(),
+		columns,
+		db: table_attrs.db,
+		allow_rebuild: table_attrs.allow_rebuild,
+		checks,
 	};
 
 	let minitable = MiniTable {
@@ -708,8 +1081,13 @@ pub fn turbosql_derive_macro(input: proc_macro::TokenStream) -> proc_macro::Toke
 				name: c.name.clone(),
 				sql_type: c.sql_type.to_string(),
 				rust_type: c.rust_type.clone(),
+				references: c.references.clone(),
+				checks: c.checks.clone(),
+				declared_sql_type: c.declared_sql_type,
+				native_text: c.native_text,
 			})
 			.collect(),
+		checks: table.checks.clone(),
 	};
 
 	create(&table, &minitable);
@@ -718,7 +1096,50 @@ pub fn turbosql_derive_macro(input: proc_macro::TokenStream) -> proc_macro::Toke
 
 	let fn_insert = insert::insert(&table);
 	let fn_update = update::update(&table);
+	let fn_upsert = upsert::upsert(&table);
+	let fns_upsert_on = upsert::upsert_on_unique_keys(&table);
 	let fn_delete = delete::delete(&table);
+	let fn_select = select::select(&table, has_skipped_fields);
+
+	// every field read through TurbosqlType must have its column type, and every field whose type name isn't known must have no TurbosqlType and be stored as JSON
+	let sql_type_checks = table
+		.columns
+		.iter()
+		.filter(|c| match c.codec() {
+			codec::Codec::Native => true,
+			codec::Codec::Json => {
+				codec::datetime_type(&c.rust_type).is_none()
+					&& !codec::is_uuid(&c.rust_type)
+					&& !codec::is_url(&c.rust_type)
+			}
+			_ => false,
+		})
+		.map(|c| {
+			let ty = parse_str::<Type>(&c.rust_type).unwrap();
+			let sql_type = match c.codec() {
+				codec::Codec::Native => {
+					let sql_type = c.sql_type.split(' ').next().unwrap();
+					quote!(::core::option::Option::Some(#sql_type))
+				}
+				_ => quote!(::core::option::Option::None),
+			};
+			let not_null = c.sql_type.ends_with(" NOT NULL");
+			let declared = c.declared_sql_type;
+			let enum_values = &c.enum_values;
+			quote_spanned!(c.span => const _: () = {
+			#[allow(unused_imports)]
+			use ::turbosql::{NoTurbosqlType as _, NotTurbosqlEnum as _};
+			::turbosql::check_sql_type(
+				#sql_type,
+				#not_null,
+				#declared,
+				::turbosql::TypeProbe::<#ty>::SQL_TYPE,
+				::turbosql::TypeProbe::<#ty>::NOT_NULL,
+				&[#(#enum_values),*],
+				::turbosql::EnumProbe::<#ty>::SQL_VALUES,
+			);
+		};)
+		});
 
 	// output tokenstream
 
@@ -727,12 +1148,206 @@ pub fn turbosql_derive_macro(input: proc_macro::TokenStream) -> proc_macro::Toke
 		impl ::turbosql::Turbosql for #table {
 			#fn_insert
 			#fn_update
+			#fn_upsert
 			#fn_delete
+			#fn_select
+		}
+
+		#[cfg(not(target_arch = "wasm32"))]
+		impl #table {
+			#(#fns_upsert_on)*
+		}
+
+		#(
+			#[cfg(not(target_arch = "wasm32"))]
+			#sql_type_checks
+		)*
+	}
+	.into()
+}
+
+/// Derive this on a single-field `struct`, such as `struct Email(String)`, to store it as its field's `TurbosqlType` and bind it as a query parameter.
+#[proc_macro_derive(TurbosqlType)]
+#[proc_macro_error]
+pub fn turbosql_type_derive_macro(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+	let ident = &input.ident;
+	let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+	let Data::Struct(DataStruct { fields, .. }) = &input.data else {
+		abort_call_site!("The TurbosqlType derive macro only supports structs with a single field");
+	};
+	if fields.len() != 1 {
+		abort!(fields, "The TurbosqlType derive macro only supports structs with a single field");
+	}
+	let field = fields.iter().next().unwrap();
+	let ty = &field.ty;
+	let (member, construct) = match &field.ident {
+		Some(name) => (quote!(#name), quote!(|value| #ident { #name: value })),
+		None => (quote!(0), quote!(#ident)),
+	};
+
+	quote! {
+		#[cfg(not(target_arch = "wasm32"))]
+		impl #impl_generics ::turbosql::TurbosqlType for #ident #ty_generics #where_clause {
+			const SQL_TYPE: &'static str = <#ty as ::turbosql::TurbosqlType>::SQL_TYPE;
+			const NOT_NULL: bool = <#ty as ::turbosql::TurbosqlType>::NOT_NULL;
+			fn to_sql_output(&self) -> ::turbosql::rusqlite::Result<::turbosql::ToSqlOutput<'_>> {
+				<#ty as ::turbosql::TurbosqlType>::to_sql_output(&self.#member)
+			}
+			fn from_sql_value(value: ::turbosql::ValueRef<'_>) -> ::turbosql::FromSqlResult<Self> {
+				<#ty as ::turbosql::TurbosqlType>::from_sql_value(value).map(#construct)
+			}
+		}
+
+		#[cfg(not(target_arch = "wasm32"))]
+		impl #impl_generics ::turbosql::ToSql for #ident #ty_generics #where_clause {
+			fn to_sql(&self) -> ::turbosql::rusqlite::Result<::turbosql::ToSqlOutput<'_>> {
+				::turbosql::TurbosqlType::to_sql_output(self)
+			}
+		}
+
+		#[cfg(not(target_arch = "wasm32"))]
+		impl #impl_generics ::turbosql::FromSql for #ident #ty_generics #where_clause {
+			fn column_result(value: ::turbosql::ValueRef<'_>) -> ::turbosql::FromSqlResult<Self> {
+				<Self as ::turbosql::TurbosqlType>::from_sql_value(value)
+			}
+		}
+	}
+	.into()
+}
+
+/// Derive this on a unit-only `enum` to store it as its variant names, or with `#[turbosql(repr = "int")]` its discriminants, and bind it as a query parameter.
+#[proc_macro_derive(TurbosqlEnum, attributes(turbosql))]
+#[proc_macro_error]
+pub fn turbosql_enum_derive_macro(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+	let ident = &input.ident;
+
+	let Data::Enum(DataEnum { variants, .. }) = &input.data else {
+		abort_call_site!("The TurbosqlEnum derive macro only supports unit-only enums");
+	};
+	if variants.is_empty() {
+		abort_call_site!("The TurbosqlEnum derive macro requires at least one variant");
+	}
+	if !input.generics.params.is_empty() {
+		abort!(input.generics, "The TurbosqlEnum derive macro doesn't support generic enums");
+	}
+
+	let mut int_repr = false;
+	for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("turbosql")) {
+		for meta in attr
+			.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+			.unwrap_or_else(|e| abort!(attr, "Unable to parse turbosql attribute: {}", e))
+		{
+			match &meta {
+				Meta::NameValue(MetaNameValue {
+					path,
+					value: Expr::Lit(ExprLit { lit: Lit::Str(repr), .. }),
+					..
+				})
+					if path.is_ident("repr") =>
+				{
+					int_repr = match repr.value().as_str() {
+						"text" => false,
+						"int" => true,
+						_ => abort!(repr, "repr must be \"text\" or \"int\""),
+					};
+				}
+				_ => abort!(meta, "Unknown turbosql attribute"),
+			}
+		}
+	}
+
+	// discriminants count up from the previous one, as in Rust
+	let mut next_discriminant = 0i64;
+	let values = variants
+		.iter()
+		.map(|variant| {
+			if !matches!(variant.fields, Fields::Unit) {
+				abort!(variant, "The TurbosqlEnum derive macro only supports unit variants");
+			}
+			let discriminant = match &variant.discriminant {
+				Some((_, expr)) => enum_int_value(expr)
+					.unwrap_or_else(|| abort!(expr, "TurbosqlEnum discriminants must be integer literals")),
+				None => next_discriminant,
+			};
+			next_discriminant = discriminant.wrapping_add(1);
+			(&variant.ident, discriminant)
+		})
+		.collect::<Vec<_>>();
+
+	let idents = values.iter().map(|(ident, _)| ident).collect::<Vec<_>>();
+	let (sql_type, sql_values, to_sql, from_sql) = if int_repr {
+		let discriminants = values.iter().map(|(_, d)| d).collect::<Vec<_>>();
+		(
+			"INTEGER",
+			discriminants.iter().map(|d| d.to_string()).collect::<Vec<_>>(),
+			quote!(match self { #(Self::#idents => ::turbosql::ToSqlOutput::from(#discriminants),)* }),
+			quote!(match value.as_i64()? {
+				#(#discriminants => Ok(Self::#idents),)*
+				other => Err(::turbosql::rusqlite::types::FromSqlError::OutOfRange(other)),
+			}),
+		)
+	} else {
+		let names = idents.iter().map(|ident| ident.to_string()).collect::<Vec<_>>();
+		(
+			"TEXT",
+			names.iter().map(|name| format!("'{}'", name)).collect(),
+			quote!(match self { #(Self::#idents => ::turbosql::ToSqlOutput::from(#names),)* }),
+			quote!(match value.as_str()? {
+				#(#names => Ok(Self::#idents),)*
+				other => Err(::turbosql::rusqlite::types::FromSqlError::Other(
+					format!("{:?} is not a variant of {}", other, stringify!(#ident)).into(),
+				)),
+			}),
+		)
+	};
+
+	quote! {
+		#[cfg(not(target_arch = "wasm32"))]
+		impl ::turbosql::TurbosqlType for #ident {
+			const SQL_TYPE: &'static str = #sql_type;
+			fn to_sql_output(&self) -> ::turbosql::rusqlite::Result<::turbosql::ToSqlOutput<'_>> {
+				Ok(#to_sql)
+			}
+			fn from_sql_value(value: ::turbosql::ValueRef<'_>) -> ::turbosql::FromSqlResult<Self> {
+				#from_sql
+			}
+		}
+
+		#[cfg(not(target_arch = "wasm32"))]
+		impl ::turbosql::TurbosqlEnum for #ident {
+			const SQL_VALUES: &'static [&'static str] = &[#(#sql_values),*];
+		}
+
+		#[cfg(not(target_arch = "wasm32"))]
+		impl ::turbosql::ToSql for #ident {
+			fn to_sql(&self) -> ::turbosql::rusqlite::Result<::turbosql::ToSqlOutput<'_>> {
+				::turbosql::TurbosqlType::to_sql_output(self)
+			}
+		}
+
+		#[cfg(not(target_arch = "wasm32"))]
+		impl ::turbosql::FromSql for #ident {
+			fn column_result(value: ::turbosql::ValueRef<'_>) -> ::turbosql::FromSqlResult<Self> {
+				<Self as ::turbosql::TurbosqlType>::from_sql_value(value)
+			}
 		}
 	}
 	.into()
 }
 
+/// The value of an integer literal, possibly negated, as in an enum discriminant or `enum_values(...)`.
+fn enum_int_value(expr: &Expr) -> Option<i64> {
+	match expr {
+		Expr::Lit(ExprLit { lit: Lit::Int(int), .. }) => int.base10_parse().ok(),
+		Expr::Unary(ExprUnary { op: UnOp::Neg(_), expr, .. }) => enum_int_value(expr).map(|value| -value),
+		Expr::Group(ExprGroup { expr, .. }) | Expr::Paren(ExprParen { expr, .. }) => enum_int_value(expr),
+		_ => None,
+	}
+}
+
 /// Convert syn::FieldsNamed to our Column type.
 fn extract_columns(fields: &FieldsNamed) -> Vec<Column> {
 	let columns = fields
@@ -740,6 +1355,19 @@ fn extract_columns(fields: &FieldsNamed) -> Vec<Column> {
 		.iter()
 		.filter_map(|f| {
 			let mut sql_default = None;
+			let mut unique = false;
+			let mut index = false;
+			let mut renamed_from = None;
+			let mut references = None;
+			let mut on_delete = None;
+			let mut checks = Vec::new();
+			let mut datetime_as = None;
+			let mut uuid_as = None;
+			let mut uuid_v7 = None;
+			let mut url_as = None;
+			let mut u64_as = None;
+			let mut declared_sql_type = None;
+			let mut enum_values = None;
 
 			for attr in &f.attrs {
 				if attr.path().is_ident("turbosql") {
@@ -748,6 +1376,121 @@ fn extract_columns(fields: &FieldsNamed) -> Vec<Column> {
 							Meta::Path(path) if path.is_ident("skip") => {
 								return None;
 							}
+							Meta::Path(path) if path.is_ident("unique") => {
+								unique = true;
+							}
+							Meta::Path(path) if path.is_ident("index") => {
+								index = true;
+							}
+							Meta::Path(path) if path.is_ident("uuid_v7") => {
+								uuid_v7 = Some(path.clone());
+							}
+							Meta::NameValue(MetaNameValue {
+								path,
+								value: Expr::Lit(ExprLit { lit: Lit::Str(old_name), .. }),
+								..
+							})
+								if path.is_ident("renamed_from") =>
+							{
+								if parse_str::<Ident>(&old_name.value()).is_err() {
+									abort!(old_name, "renamed_from must be a column name");
+								}
+								renamed_from = Some(old_name.value());
+							}
+							Meta::NameValue(MetaNameValue {
+								path,
+								value: Expr::Lit(ExprLit { lit: Lit::Str(check), .. }),
+								..
+							})
+								if path.is_ident("check") =>
+							{
+								checks.push(check.value());
+							}
+							Meta::NameValue(MetaNameValue {
+								path,
+								value: Expr::Lit(ExprLit { lit: Lit::Str(storage), .. }),
+								..
+							})
+								if path.is_ident("datetime_as") =>
+							{
+								if !["text", "unix_ms"].contains(&storage.value().as_str()) {
+									abort!(storage, "datetime_as must be \"text\" or \"unix_ms\"");
+								}
+								datetime_as = Some(storage.clone());
+							}
+							Meta::NameValue(MetaNameValue {
+								path,
+								value: Expr::Lit(ExprLit { lit: Lit::Str(storage), .. }),
+								..
+							})
+								if path.is_ident("uuid_as") =>
+							{
+								if !["blob", "text"].contains(&storage.value().as_str()) {
+									abort!(storage, "uuid_as must be \"blob\" or \"text\"");
+								}
+								uuid_as = Some(storage.clone());
+							}
+							Meta::NameValue(MetaNameValue {
+								path,
+								value: Expr::Lit(ExprLit { lit: Lit::Str(storage), .. }),
+								..
+							})
+								if path.is_ident("url_as") =>
+							{
+								if storage.value() != "text" {
+									abort!(storage, "url_as must be \"text\"");
+								}
+								url_as = Some(storage.clone());
+							}
+							Meta::NameValue(MetaNameValue {
+								path,
+								value: Expr::Lit(ExprLit { lit: Lit::Str(storage), .. }),
+								..
+							})
+								if path.is_ident("u64_as") =>
+							{
+								if !["blob_be", "checked_i64", "text"].contains(&storage.value().as_str()) {
+									abort!(storage, "u64_as must be \"blob_be\", \"checked_i64\" or \"text\"");
+								}
+								u64_as = Some(storage.clone());
+							}
+							Meta::NameValue(MetaNameValue {
+								path,
+								value: Expr::Lit(ExprLit { lit: Lit::Str(sql_type), .. }),
+								..
+							})
+								if path.is_ident("sql_type") =>
+							{
+								if !["INTEGER", "REAL", "TEXT", "BLOB", "ANY"].contains(&sql_type.value().as_str()) {
+									abort!(sql_type, "sql_type must be \"INTEGER\", \"REAL\", \"TEXT\", \"BLOB\" or \"ANY\"");
+								}
+								declared_sql_type = Some(sql_type.clone());
+							}
+							Meta::List(list) if list.path.is_ident("enum_values") => {
+								enum_values = Some(extract_enum_values(list));
+							}
+							Meta::NameValue(MetaNameValue { path, value: Expr::Path(target), .. })
+								if path.is_ident("references") =>
+							{
+								let target = target.path.segments.last().unwrap();
+								references = Some((target.ident.clone(), target.ident.to_string().to_lowercase()));
+							}
+							Meta::NameValue(MetaNameValue {
+								path,
+								value: Expr::Lit(ExprLit { lit: Lit::Str(action), .. }),
+								..
+							})
+								if path.is_ident("on_delete") =>
+							{
+								let sql = action.value().to_uppercase();
+								if !["CASCADE", "SET NULL", "SET DEFAULT", "RESTRICT", "NO ACTION"].contains(&sql.as_str()) {
+									abort!(
+										action,
+										"on_delete must be one of \"cascade\", \"set null\", \"set default\", \"restrict\" or \"no action\""
+									);
+								}
+								on_delete = Some((action.clone(), sql));
+							}
 							Meta::NameValue(MetaNameValue { path, value: Expr::Lit(ExprLit { lit, .. }), .. })
 								if path.is_ident("sql_default") =>
 							{
@@ -780,15 +1523,127 @@ fn extract_columns(fields: &FieldsNamed) -> Vec<Column> {
 
 			let ty = &f.ty;
 			let ty_str = quote!(#ty).to_string();
+			// type names without paths, so that e.g. std::string::String is a String
+			let ty_key = codec::without_paths(&ty_str);
+
+			if let Some(captures) = OPTION_REF_RE.captures(&ty_str) {
+				if references.is_some() {
+					abort!(f, "Option<Ref<T>> fields already reference T; remove #[turbosql(references = ...)]");
+				}
+				references = Some((ident.clone().unwrap(), captures[1].to_lowercase()));
+			} else if REF_RE.is_match(&ty_str) {
+				abort!(ty, "Ref<T> fields must be Option<Ref<T>>, since existing rows have nothing to reference");
+			}
 
-			let (sql_type, default_example) = match (
+			let datetime = codec::datetime_type(&ty_str).filter(|_| datetime_as.is_some());
+			let unix_ms = match (&datetime_as, &datetime) {
+				(Some(storage), None) => {
+					abort!(storage, "datetime_as requires a chrono, time or jiff date or time field, with that crate's feature enabled")
+				}
+				(Some(storage), Some(datetime)) if storage.value() == "unix_ms" && !datetime.instant => abort!(
+					storage,
+					"datetime_as = \"unix_ms\" requires an instant: chrono::DateTime<Utc>, time::OffsetDateTime or jiff::Timestamp"
+				),
+				(Some(storage), _) => storage.value() == "unix_ms",
+				(None, _) => false,
+			};
+
+			let uuid = codec::is_uuid(&ty_str);
+			let uuid_text = match &uuid_as {
+				Some(storage) if !uuid => abort!(storage, "uuid_as requires a Uuid field, with the uuid feature enabled"),
+				Some(storage) => storage.value() == "text",
+				None => false,
+			};
+			if let Some(path) = &uuid_v7 {
+				if !uuid || !ty_key.starts_with("Option < ") {
+					abort!(path, "uuid_v7 requires an Option<Uuid> field, with the uuid feature enabled");
+				}
+				unique = true;
+			}
+			let url = codec::is_url(&ty_str);
+			if let Some(storage) = &url_as {
+				if !url {
+					abort!(storage, "url_as requires a Url field, with the url feature enabled");
+				}
+			}
+
+			// dates, times, UUIDs and URLs were stored as JSON before they had native columns, so fields keep that unless they opt in
+			let json = (datetime_as.is_none() && codec::datetime_type(&ty_str).is_some())
+				|| (uuid && uuid_as.is_none() && uuid_v7.is_none())
+				|| (url && url_as.is_none());
+
+			if let Some(storage) = &u64_as {
+				if codec::base_type(&ty_str) != "u64" {
+					abort!(storage, "u64_as requires a u64 field");
+				}
+			}
+
+			// a TurbosqlEnum column, converted and checked like sql_type, that only takes the listed values
+			let enum_values = enum_values.map(|(span, sql_type, values): (Span, _, Vec<String>)| {
+				if let Some(sql_type) = &declared_sql_type {
+					abort!(sql_type, "enum_values already sets the column type; remove sql_type");
+				}
+				declared_sql_type = Some(LitStr::new(sql_type, span));
+				checks.insert(0, format!("{} IN ({})", name, values.join(", ")));
+				values
+			});
+
+			if let (Some(sql_type), Some(storage)) = (&declared_sql_type, datetime_as.as_ref().or(uuid_as.as_ref()).or(url_as.as_ref()).or(u64_as.as_ref())) {
+				abort!(storage, "{:?} can't be combined with sql_type = {:?}", storage.value(), sql_type.value());
+			}
+
+			let (sql_type, default_example) = if let Some(sql_type) = &declared_sql_type {
+				// converted through TurbosqlType, and checked against its SQL_TYPE below
+				match (sql_type.value().as_str(), ty_key.starts_with("Option < ")) {
+					("INTEGER", true) => ("INTEGER", "0"),
+					("INTEGER", false) => ("INTEGER NOT NULL", "0"),
+					("REAL", true) => ("REAL", "0.0"),
+					("REAL", false) => ("REAL NOT NULL", "0.0"),
+					("TEXT", true) => ("TEXT", "''"),
+					("TEXT", false) => ("TEXT NOT NULL", "''"),
+					("BLOB", true) => ("BLOB", "x''"),
+					("BLOB", false) => ("BLOB NOT NULL", "x''"),
+					(_, true) => ("ANY", "NULL"),
+					(_, false) => ("ANY NOT NULL", "0"),
+				}
+			} else { match (
 				name.as_str(),
-				if OPTION_U8_ARRAY_RE.is_match(&ty_str) {
+				if let Some(storage) = &u64_as {
+					match (ty_key.starts_with("Option < "), storage.value().as_str()) {
+						(true, "blob_be") => "Option < u64 blob_be >",
+						(false, "blob_be") => "u64 blob_be",
+						(true, "checked_i64") => "Option < u64 checked_i64 >",
+						(false, "checked_i64") => "u64 checked_i64",
+						(true, _) => "Option < u64 text >",
+						(false, _) => "u64 text",
+					}
+				} else if uuid && !json {
+					match (ty_key.starts_with("Option < "), uuid_text) {
+						(true, false) => "Option < uuid >",
+						(false, false) => "uuid",
+						(true, true) => "Option < uuid text >",
+						(false, true) => "uuid text",
+					}
+				} else if url && !json {
+					match ty_key.starts_with("Option < ") {
+						true => "Option < url >",
+						false => "url",
+					}
+				} else if datetime.is_some() {
+					match (ty_key.starts_with("Option < "), unix_ms) {
+						(true, false) => "Option < datetime >",
+						(false, false) => "datetime",
+						(true, true) => "Option < datetime unix_ms >",
+						(false, true) => "datetime unix_ms",
+					}
+				} else if OPTION_U8_ARRAY_RE.is_match(&ty_key) {
 					"Option < [u8; _] >"
-				} else if U8_ARRAY_RE.is_match(&ty_str) {
+				} else if U8_ARRAY_RE.is_match(&ty_key) {
 					"[u8; _]"
+				} else if OPTION_REF_RE.is_match(&ty_str) {
+					"Option < Ref < _ > >"
 				} else {
-					ty_str.as_str()
+					ty_key.as_str()
 				},
 			) {
 				("rowid", "Option < i64 >") => ("INTEGER PRIMARY KEY", "NULL"),
@@ -808,6 +1663,17 @@ fn extract_columns(fields: &FieldsNamed) -> Vec<Column> {
 				(_, "i64") => ("INTEGER NOT NULL", "0"),
 				(_, "Option < u64 >") => abort!(ty, SQLITE_U64_ERROR),
 				(_, "u64") => abort!(ty, SQLITE_U64_ERROR),
+				// big-endian bytes compare in numeric order
+				(_, "Option < u64 blob_be >") => ("BLOB", "x'0000000000000000'"),
+				(_, "u64 blob_be") => ("BLOB NOT NULL", "x'0000000000000000'"),
+				// rusqlite's u64 ToSql and FromSql check the i64 range
+				(_, "Option < u64 checked_i64 >") => ("INTEGER", "0"),
+				(_, "u64 checked_i64") => ("INTEGER NOT NULL", "0"),
+				(_, "Option < u64 text >") => ("TEXT", "'0'"),
+				(_, "u64 text") => ("TEXT NOT NULL", "'0'"),
+				// rusqlite stores i128 as 16 big-endian bytes with the sign bit flipped, which also sort numerically
+				(_, "Option < i128 >") if cfg!(feature = "i128_blob") => ("BLOB", "x'80000000000000000000000000000000'"),
+				(_, "i128") if cfg!(feature = "i128_blob") => ("BLOB NOT NULL", "x'80000000000000000000000000000000'"),
 				(_, "Option < f64 >") => ("REAL", "0.0"),
 				(_, "f64") => ("REAL NOT NULL", "0.0"),
 				(_, "Option < f32 >") => ("REAL", "0.0"),
@@ -823,21 +1689,56 @@ fn extract_columns(fields: &FieldsNamed) -> Vec<Column> {
 				(_, "Vec < u8 >") => ("BLOB NOT NULL", "''"),
 				(_, "Option < [u8; _] >") => ("BLOB", "b\"\\x00\\x01\\xff\""),
 				(_, "[u8; _]") => ("BLOB NOT NULL", "''"),
+				(_, "Option < Ref < _ > >") => ("INTEGER", "0"),
+				// ISO-8601 text, which sorts chronologically within one type
+				(_, "Option < datetime >") => ("TEXT", datetime.as_ref().unwrap().text_default),
+				(_, "datetime") => ("TEXT NOT NULL", datetime.as_ref().unwrap().text_default),
+				(_, "Option < datetime unix_ms >") => ("INTEGER", "0"),
+				(_, "datetime unix_ms") => ("INTEGER NOT NULL", "0"),
+				// rusqlite stores Uuid as 16 big-endian bytes, so UUIDv7s sort by creation time
+				(_, "Option < uuid >") => ("BLOB", "x'00000000000000000000000000000000'"),
+				(_, "uuid") => ("BLOB NOT NULL", "x'00000000000000000000000000000000'"),
+				(_, "Option < uuid text >") => ("TEXT", "'00000000-0000-0000-0000-000000000000'"),
+				(_, "uuid text") => ("TEXT NOT NULL", "'00000000-0000-0000-0000-000000000000'"),
+				(_, "Option < url >") => ("TEXT", "'about:blank'"),
+				(_, "url") => ("TEXT NOT NULL", "'about:blank'"),
 				_ => {
 					// JSON-serialized
-					if ty_str.starts_with("Option < ") {
+					if ty_key.starts_with("Option < ") {
 						("TEXT", "\"\"")
 					} else {
 						("TEXT NOT NULL", "''")
 					}
 				}
-			};
+			} };
 
 			if sql_default.is_none() && sql_type.ends_with("NOT NULL") {
-				sql_default = Some(default_example.into());
+				// existing rows must satisfy an enum column's CHECK
+				sql_default = Some(enum_values.as_ref().map_or(default_example.into(), |values| values[0].clone()));
 				// abort!(f, "Field `{}` has no default value and is not nullable. Either add a default value with e.g. #[turbosql(sql_default = {default_example})] or make it Option<{ty_str}>.", name);
 			}
 
+			// a foreign key column must be nullable, since ADD COLUMN can't give existing rows a valid reference
+			if let Some((target, _)) = &references {
+				if name == "rowid" || sql_type != "INTEGER" {
+					abort!(target, "references = ... requires an Option<i64> field");
+				}
+			}
+
+			let references = match (references, on_delete) {
+				(Some((_, table)), None) => Some(format!("{}(rowid)", table)),
+				(Some((_, table)), Some((_, action))) => Some(format!("{}(rowid) ON DELETE {}", table, action)),
+				(None, Some((action, _))) => abort!(action, "on_delete requires an Option<Ref<T>> field or references = T"),
+				(None, None) => None,
+			};
+
+			// TEXT columns other than String were JSON before this was recorded
+			let native_text = sql_type.starts_with("TEXT")
+				&& !codec::is_string(&ty_str)
+				&& (ty_key == "String"
+					|| ty_key == "Option < String >"
+					|| (datetime.is_some() || uuid || url) && !json);
+
 			Some(Column {
 				ident: ident.clone().unwrap(),
 				span: ty.span(),
@@ -845,6 +1746,15 @@ fn extract_columns(fields: &FieldsNamed) -> Vec<Column> {
 				name,
 				sql_type,
 				sql_default,
+				unique,
+				index,
+				renamed_from,
+				references,
+				checks,
+				uuid_v7: uuid_v7.is_some(),
+				declared_sql_type: declared_sql_type.is_some(),
+				native_text,
+				enum_values: enum_values.unwrap_or_default(),
 			})
 		})
 		.collect::<Vec<_>>();
@@ -860,9 +1770,235 @@ fn extract_columns(fields: &FieldsNamed) -> Vec<Column> {
 		abort_call_site!("derive(Turbosql) structs must include a 'rowid: Option<i64>' field")
 	};
 
+	if let Some(c) = columns.iter().find(|c| c.name == "rowid" && c.unique) {
+		abort!(c.ident, "rowid is already unique; remove #[turbosql(unique)]");
+	}
+
+	if let Some(c) = columns.iter().find(|c| c.name == "rowid" && c.index) {
+		abort!(c.ident, "rowid is already indexed; remove #[turbosql(index)]");
+	}
+
+	for c in &columns {
+		match &c.renamed_from {
+			Some(_) if c.name == "rowid" => abort!(c.ident, "rowid cannot be renamed"),
+			Some(old_name) if old_name == "rowid" || columns.iter().any(|other| &other.name == old_name) => {
+				abort!(c.ident, "renamed_from = {:?} is still a column of this struct", old_name)
+			}
+			_ => (),
+		}
+	}
+
 	columns
 }
 
+#[derive(Debug, Default)]
+struct TableAttrs {
+	unique_keys: Vec<Vec<String>>,
+	indexes: Vec<Vec<String>>,
+	/// Struct-level `CHECK` expressions, with the index of the column each is attached to.
+	checks: Vec<(usize, String)>,
+	db: Option<String>,
+	allow_rebuild: bool,
+}
+
+/// Identifiers in the SQL expression `sql`, unquoted, skipping string and blob literals, numbers and comments.
+fn sql_identifiers(sql: &str) -> Vec<String> {
+	let mut identifiers = Vec::new();
+	let mut chars = sql.chars().peekable();
+
+	// the text up to an unescaped `close`, where a doubled `close` stands for itself
+	let quoted = |chars: &mut std::iter::Peekable<std::str::Chars>, close: char| {
+		let mut text = String::new();
+		while let Some(c) = chars.next() {
+			match c {
+				c if c == close && chars.peek() == Some(&close) && close != ']' => {
+					chars.next();
+					text.push(c);
+				}
+				c if c == close => break,
+				c => text.push(c),
+			}
+		}
+		text
+	};
+
+	while let Some(c) = chars.next() {
+		match c {
+			'\'' => {
+				quoted(&mut chars, '\'');
+			}
+			'"' => identifiers.push(quoted(&mut chars, '"')),
+			'`' => identifiers.push(quoted(&mut chars, '`')),
+			'[' => identifiers.push(quoted(&mut chars, ']')),
+			'-' if chars.peek() == Some(&'-') => {
+				chars.by_ref().find(|&c| c == '\n');
+			}
+			'/' if chars.peek() == Some(&'*') => {
+				chars.next();
+				let mut previous = ' ';
+				chars.by_ref().find(|&c| std::mem::replace(&mut previous, c) == '*' && c == '/');
+			}
+			c if c.is_ascii_digit() || (c == '.' && chars.peek().is_some_and(char::is_ascii_digit)) => {
+				while chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '.' || *c == '_').is_some() {}
+			}
+			c if c.is_alphabetic() || c == '_' => {
+				let mut identifier = c.to_string();
+				while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_' || *c == '$') {
+					identifier.push(c);
+				}
+				// X'0F' is a blob literal
+				if identifier.eq_ignore_ascii_case("x") && chars.peek() == Some(&'\'') {
+					continue;
+				}
+				identifiers.push(identifier);
+			}
+			_ => (),
+		}
+	}
+
+	identifiers
+}
+
+/// Index of the last column a struct-level `check = "..."` mentions, which it is attached to when that column is added by `ALTER TABLE`.
+fn check_column(check: &LitStr, columns: &[Column]) -> usize {
+	sql_identifiers(&check.value())
+		.iter()
+		.filter_map(|ident| {
+			columns.iter().position(|c| c.name.eq_ignore_ascii_case(ident) && c.name != "rowid")
+		})
+		.max()
+		.unwrap_or_else(|| abort!(check, "check must mention at least one column other than rowid"))
+}
+
+/// Split struct-level checks into table constraints, which are returned, and checks attached to the column they were given for.
+///
+/// A check is a table constraint if the table is created or rebuilt with it. `ALTER TABLE` can't add table constraints, so a check added to an existing table is attached to its column if that column is added along with it.
+fn place_struct_checks(
+	table_name: &str,
+	db: Option<&str>,
+	columns: &mut [Column],
+	checks: Vec<(usize, String)>,
+) -> Vec<String> {
+	let migrations_toml = read_migrations_toml().for_db(db);
+	let existing = migrations_toml.migrations_append_only.unwrap_or_default();
+	let previous =
+		migrations_toml.output_generated_tables_do_not_edit.and_then(|mut t| t.remove(table_name));
+
+	checks
+		.into_iter()
+		.filter_map(|(i, check)| {
+			let column = &mut columns[i];
+			let attached_before = previous
+				.iter()
+				.flat_map(|p| &p.columns)
+				.any(|p| p.name == column.name && p.checks.contains(&check));
+			let added_with_check = table_created(&existing, table_name).is_some()
+				&& !column_exists(&existing, table_name, &column.name)
+				&& !previous.iter().any(|p| p.checks.contains(&check));
+
+			if attached_before || added_with_check {
+				column.checks.push(check);
+				None
+			} else {
+				Some(check)
+			}
+		})
+		.collect()
+}
+
+/// Parse a field's `enum_values("A", "B")` or `enum_values(0, 1)` into its column type and SQL literals.
+fn extract_enum_values(list: &MetaList) -> (Span, &'static str, Vec<String>) {
+	let exprs =
+		list.parse_args_with(Punctuated::<Expr, Token![,]>::parse_terminated).unwrap_or_else(|e| {
+			abort!(
+				list,
+				"Expected variant names or discriminants, e.g. enum_values(\"Active\", \"Archived\"): {}",
+				e
+			)
+		});
+	if exprs.is_empty() {
+		abort!(list, "Expected at least one value, e.g. enum_values(\"Active\", \"Archived\")");
+	}
+	let values = exprs.iter().map(|expr| match expr {
+		Expr::Lit(ExprLit { lit: Lit::Str(name), .. }) => {
+			("TEXT", format!("'{}'", name.value().replace('\'', "''")))
+		}
+		expr => match enum_int_value(expr) {
+			Some(value) => ("INTEGER", value.to_string()),
+			None => abort!(expr, "enum_values must be all variant names or all integer discriminants"),
+		},
+	});
+	let (sql_types, values): (Vec<_>, Vec<_>) = values.unzip();
+	if sql_types.iter().any(|sql_type| sql_type != &sql_types[0]) {
+		abort!(list, "enum_values must be all variant names or all integer discriminants");
+	}
+	(list.span(), sql_types[0], values)
+}
+
+/// Parse the column names of a struct-level `unique(a, b)`, `unique_index(a, b)` or `index(a, b)`.
+fn extract_key_columns(list: &MetaList, columns: &[Column]) -> Vec<String> {
+	let kind = list.path.get_ident().unwrap();
+	let idents = list
+		.parse_args_with(Punctuated::<Ident, Token![,]>::parse_terminated)
+		.unwrap_or_else(|e| abort!(list, "Expected column names, e.g. {}(a, b): {}", kind, e));
+	if idents.is_empty() {
+		abort!(list, "Expected at least one column name, e.g. {}(a, b)", kind);
+	}
+	for ident in &idents {
+		if ident == "rowid" || !columns.iter().any(|c| ident == &c.name) {
+			abort!(ident, "Unknown column `{}` in {}(...)", ident, kind);
+		}
+	}
+	idents.iter().map(|i| i.to_string()).collect()
+}
+
+/// Parse struct-level `#[turbosql(...)]` attributes.
+fn extract_table_attrs(attrs: &[Attribute], columns: &[Column]) -> TableAttrs {
+	let mut table_attrs = TableAttrs::default();
+
+	for attr in attrs {
+		if attr.path().is_ident("turbosql") {
+			for meta in attr
+				.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+				.unwrap_or_else(|e| abort!(attr, "Unable to parse turbosql attribute: {}", e))
+			{
+				match &meta {
+					Meta::List(list) if list.path.is_ident("unique") || list.path.is_ident("unique_index") => {
+						table_attrs.unique_keys.push(extract_key_columns(list, columns));
+					}
+					Meta::List(list) if list.path.is_ident("index") => {
+						table_attrs.indexes.push(extract_key_columns(list, columns));
+					}
+					Meta::NameValue(MetaNameValue {
+						path,
+						value: Expr::Lit(ExprLit { lit: Lit::Str(check), .. }),
+						..
+					})
+						if path.is_ident("check") =>
+					{
+						table_attrs.checks.push((check_column(check, columns), check.value()));
+					}
+					Meta::Path(path) if path.is_ident("allow_rebuild") => {
+						table_attrs.allow_rebuild = true;
+					}
+					Meta::NameValue(MetaNameValue {
+						path,
+						value: Expr::Lit(ExprLit { lit: Lit::Str(name), .. }),
+						..
+					})
+						if path.is_ident("db") =>
+					{
+						table_attrs.db = Some(validate_db_name(name));
+					}
+					_ => abort!(meta, "Unknown turbosql attribute"),
+				}
+			}
+		}
+	}
+
+	table_attrs
+}
+
 use std::fs;
 
 /// CREATE TABLE
@@ -871,11 +2007,17 @@ fn create(table: &Table, minitable: &MiniTable) {
 
 	let sql = makesql_create(table);
 
-	rusqlite::Connection::open_in_memory().unwrap().execute(&sql, params![]).unwrap_or_else(|e| {
+	let conn = rusqlite::Connection::open_in_memory().unwrap();
+
+	conn.execute(&sql, params![]).unwrap_or_else(|e| {
 		abort_call_site!("Error validating auto-generated CREATE TABLE statement: {} {:#?}", sql, e)
 	});
 
-	let target_migrations = make_migrations(table);
+	for sql in make_indexes(table) {
+		conn.execute(&sql, params![]).unwrap_or_else(|e| {
+			abort_call_site!("Error validating auto-generated CREATE INDEX statement: {} {:#?}", sql, e)
+		});
+	}
 
 	// read in the existing migrations from toml
 
@@ -892,14 +2034,74 @@ fn create(table: &Table, minitable: &MiniTable) {
 		String::new()
 	};
 
-	let source_migrations_toml: MigrationsToml = toml::from_str(&old_toml_str).unwrap_or_else(|e| {
+	let mut root_migrations_toml: MigrationsToml = toml::from_str(&old_toml_str).unwrap_or_else(|e| {
 		abort_call_site!("Unable to decode toml in {}: {:?}", migrations_toml_path_lossy, e)
 	});
 
+	// named databases keep their migrations in their own [databases.name] section
+
+	let source_migrations_toml = root_migrations_toml.clone().for_db(table.db.as_deref());
+
 	// add any migrations that aren't already present
 
 	let mut output_migrations = source_migrations_toml.migrations_append_only.unwrap_or_default();
 
+	// compare with the columns from the last compile, for changes ALTER TABLE can't make
+
+	let previous_table = source_migrations_toml
+		.output_generated_tables_do_not_edit
+		.as_ref()
+		.and_then(|tables| tables.get(&table.name));
+	let previous_columns = previous_table.map(|previous| rebuild::previous_columns(table, previous));
+
+	let rebuilding = match (&previous_columns, previous_table) {
+		(Some(previous), Some(previous_table)) => {
+			let (changed, removed) = rebuild::incompatible_changes(table, previous);
+			let checks_changed = rebuild::checks_changed(table, previous_table);
+			if checks_changed && !table.allow_rebuild {
+				abort!(
+					table.span,
+					"The struct-level checks of `{}` changed from {:?} to {:?}. SQLite can't change a table's constraints in place; add #[turbosql(allow_rebuild)] to the struct to migrate it by rebuilding the table.",
+					table.ident,
+					previous_table.checks,
+					table.checks
+				);
+			}
+			if let (Some((c, previous_definition)), false) = (changed.first(), table.allow_rebuild) {
+				abort!(
+					c.ident,
+					"Column `{}` changed from {} to {}. SQLite can't change a column's type or constraints in place; add #[turbosql(allow_rebuild)] to the struct to migrate it by rebuilding the table.",
+					c.name,
+					previous_definition,
+					c.sql_definition(&table.name)
+				);
+			}
+			table.allow_rebuild && !(changed.is_empty() && removed.is_empty() && !checks_changed)
+		}
+		_ => false,
+	};
+
+	// a column that already exists, or that a rebuild creates, must not be added again
+
+	let adds_existing_column = |m: &String| {
+		table.columns.iter().any(|c| {
+			m.starts_with(&format!("ALTER TABLE {} ADD COLUMN {} ", table.name, c.name))
+				&& (rebuilding
+					|| previous_columns
+						.iter()
+						.flatten()
+						.any(|p| p.name == c.name && rebuild::same_definition(p, c)))
+		})
+	};
+
+	let target_migrations = make_migrations(table, &output_migrations)
+		.into_iter()
+		.filter(|m| {
+			output_migrations.iter().any(|source_m| source_m == m || source_m == &format!("--{}", m))
+				|| !adds_existing_column(m)
+		})
+		.collect::<Vec<_>>();
+
 	#[allow(clippy::search_is_some)]
 	target_migrations.iter().for_each(|target_m| {
 		if output_migrations
@@ -911,15 +2113,28 @@ fn create(table: &Table, minitable: &MiniTable) {
 		}
 	});
 
+	if rebuilding {
+		let rebuild_migrations =
+			rebuild::rebuild_migrations(table, previous_columns.as_ref().unwrap(), &output_migrations);
+		output_migrations.extend(rebuild_migrations);
+	} else {
+		// a column whose values are stored differently, but in the same column type, is converted in place
+		for (c, p) in table
+			.columns
+			.iter()
+			.filter_map(|c| Some((c, previous_columns.iter().flatten().find(|p| p.name == c.name)?)))
+		{
+			if let Some(value) = rebuild::conversion(p, c) {
+				output_migrations.push(rebuild::verify_conversion(table, p, c, &value));
+				output_migrations.push(format!("UPDATE {} SET {} = {}", table.name, c.name, value));
+			}
+		}
+	}
+
 	let mut tables = source_migrations_toml.output_generated_tables_do_not_edit.unwrap_or_default();
 	tables.insert(table.name.clone(), minitable.clone());
 
-	// save to toml
-
-	let mut new_toml_str = String::new();
-	let serializer = toml::Serializer::pretty(&mut new_toml_str);
-
-	MigrationsToml {
+	let migrations_toml = MigrationsToml {
 		output_generated_schema_for_your_information_do_not_edit: Some(format!(
 			"  {}\n",
 			migrations_to_schema(&output_migrations)
@@ -931,9 +2146,27 @@ fn create(table: &Table, minitable: &MiniTable) {
 		)),
 		migrations_append_only: Some(output_migrations),
 		output_generated_tables_do_not_edit: Some(tables),
+		databases: source_migrations_toml.databases,
+	};
+
+	match &table.db {
+		None => root_migrations_toml = migrations_toml,
+		Some(db) => {
+			root_migrations_toml
+				.databases
+				.get_or_insert_with(Default::default)
+				.insert(db.clone(), migrations_toml);
+		}
 	}
-	.serialize(serializer)
-	.unwrap_or_else(|e| abort_call_site!("Unable to serialize migrations toml: {:?}", e));
+
+	// save to toml
+
+	let mut new_toml_str = String::new();
+	let serializer = toml::Serializer::pretty(&mut new_toml_str);
+
+	root_migrations_toml
+		.serialize(serializer)
+		.unwrap_or_else(|e| abort_call_site!("Unable to serialize migrations toml: {:?}", e));
 
 	let new_toml_str = format!("# This file is auto-generated by Turbosql.\n# It is used to create and apply automatic schema migrations.\n# It should be checked into source control.\n# Modifying it by hand may be dangerous; see the docs.\n\n{}", &new_toml_str);
 
@@ -951,8 +2184,13 @@ fn create(table: &Table, minitable: &MiniTable) {
 }
 
 fn makesql_create(table: &Table) -> String {
-	let columns =
-		table.columns.iter().map(|c| format!("{} {}", c.name, c.sql_type)).collect::<Vec<_>>().join(",");
+	let columns = table
+		.columns
+		.iter()
+		.map(|c| format!("{} {}", c.name, c.sql_definition(&table.name)))
+		.chain(table.checks.iter().map(|check| check_constraint(&table.name, check)))
+		.collect::<Vec<_>>()
+		.join(",");
 
 	if cfg!(feature = "sqlite-compat-no-strict-tables") {
 		format!("CREATE TABLE {} ({})", table.name, columns)
@@ -961,33 +2199,121 @@ fn makesql_create(table: &Table) -> String {
 	}
 }
 
-fn make_migrations(table: &Table) -> Vec<String> {
-	let sql = if cfg!(feature = "sqlite-compat-no-strict-tables") {
-		format!("CREATE TABLE {} (rowid INTEGER PRIMARY KEY)", table.name)
+/// Whether `existing` migrations have added `column` to `table`, either directly, by renaming another column to it, or by rebuilding the table with it.
+fn column_exists(existing: &[String], table: &str, column: &str) -> bool {
+	let add = format!("ALTER TABLE {} ADD COLUMN {} ", table, column);
+	let rename = format!("ALTER TABLE {} RENAME COLUMN ", table);
+	let rename_to = format!(" TO {}", column);
+
+	existing.iter().map(|m| m.strip_prefix("--").unwrap_or(m)).any(|m| {
+		m.starts_with(&add)
+			|| (m.starts_with(&rename) && m.ends_with(&rename_to))
+			|| rebuild::created_columns(m, table).is_some_and(|mut columns| columns.any(|c| c == column))
+	})
+}
+
+/// The `CREATE TABLE` statement that `existing` migrations created `table` with.
+fn table_created<'a>(existing: &'a [String], table: &str) -> Option<&'a str> {
+	let create = format!("CREATE TABLE {} (", table);
+	existing.iter().map(|m| m.strip_prefix("--").unwrap_or(m)).find(|m| m.starts_with(&create))
+}
+
+/// `CREATE TABLE` for a new table. A table with struct-level checks is created with all its columns, so the checks can be table constraints; others start with just `rowid` and get their columns from `ALTER TABLE`.
+fn create_table_sql(table: &Table) -> String {
+	let columns = match table.checks.is_empty() {
+		true => "rowid INTEGER PRIMARY KEY".to_string(),
+		false => rebuild::column_defs(table),
+	};
+
+	if cfg!(feature = "sqlite-compat-no-strict-tables") {
+		format!("CREATE TABLE {} ({})", table.name, columns)
 	} else {
-		format!("CREATE TABLE {} (rowid INTEGER PRIMARY KEY) STRICT", table.name)
+		format!("CREATE TABLE {} ({}) STRICT", table.name, columns)
+	}
+}
+
+/// `ALTER TABLE ... RENAME COLUMN` for a `renamed_from` column, if it has been or should be renamed rather than added.
+fn renamed_column_sql(table: &Table, column: &Column, existing: &[String]) -> Option<String> {
+	let old_name = column.renamed_from.as_ref()?;
+	let sql = format!("ALTER TABLE {} RENAME COLUMN {} TO {}", table.name, old_name, column.name);
+
+	let already_renamed = existing.iter().any(|m| m.strip_prefix("--").unwrap_or(m) == sql);
+	let needs_rename = column_exists(existing, &table.name, old_name)
+		&& !column_exists(existing, &table.name, &column.name);
+
+	(already_renamed || needs_rename).then_some(sql)
+}
+
+fn make_migrations(table: &Table, existing: &[String]) -> Vec<String> {
+	let sql = match table_created(existing, &table.name) {
+		Some(sql) => sql.to_string(),
+		None => create_table_sql(table),
 	};
+	let created_columns = rebuild::created_columns(&sql, &table.name)
+		.map(|columns| columns.collect::<Vec<_>>())
+		.unwrap_or_default();
 
-	let mut vec = vec![sql];
+	let mut vec = vec![sql.clone()];
 
 	let mut alters = table
 		.columns
 		.iter()
-		.filter_map(|c| match (c.name.as_str(), c.sql_type, &c.sql_default) {
-			("rowid", "INTEGER PRIMARY KEY", _) => None,
-			(_, _, None) => Some(format!("ALTER TABLE {} ADD COLUMN {} {}", table.name, c.name, c.sql_type)),
-			(_, _, Some(sql_default)) => Some(format!(
-				"ALTER TABLE {} ADD COLUMN {} {} DEFAULT {}",
-				table.name, c.name, c.sql_type, sql_default
-			)),
+		.filter_map(|c| {
+			// a renamed column keeps its data, unless it never existed under its old name
+			if let Some(sql) = renamed_column_sql(table, c, existing) {
+				return Some(sql);
+			}
+			match (c.name.as_str(), c.sql_type, &c.sql_default) {
+				("rowid", "INTEGER PRIMARY KEY", _) => None,
+				(name, _, _) if created_columns.contains(&name) => None,
+				(_, _, None) => Some(format!(
+					"ALTER TABLE {} ADD COLUMN {} {}",
+					table.name,
+					c.name,
+					c.sql_definition(&table.name)
+				)),
+				(_, _, Some(sql_default)) => Some(format!(
+					"ALTER TABLE {} ADD COLUMN {} {} DEFAULT {}",
+					table.name,
+					c.name,
+					c.sql_definition(&table.name),
+					sql_default
+				)),
+			}
 		})
 		.collect::<Vec<_>>();
 
 	vec.append(&mut alters);
 
+	vec.extend(make_indexes(table));
+
 	vec
 }
 
+fn make_indexes(table: &Table) -> Vec<String> {
+	let unique = table.unique_keys.iter().map(|key| {
+		format!(
+			"CREATE UNIQUE INDEX IF NOT EXISTS {}_{}_unique ON {} ({})",
+			table.name,
+			key.join("_"),
+			table.name,
+			key.join(", ")
+		)
+	});
+
+	let indexes = table.indexes.iter().map(|key| {
+		format!(
+			"CREATE INDEX IF NOT EXISTS {}_{}_index ON {} ({})",
+			table.name,
+			key.join("_"),
+			table.name,
+			key.join(", ")
+		)
+	});
+
+	unique.chain(indexes).collect()
+}
+
 fn migrations_toml_path() -> std::path::PathBuf {
 	let mut path = std::path::PathBuf::from(env!("OUT_DIR"));
 	while path.file_name() != Some(std::ffi::OsStr::new("target")) {
@@ -1044,4 +2370,325 @@ mod tests {
 
 		assert!(!columns.iter().any(|c| c.name == "skipped"));
 	}
+
+	#[test]
+	fn test_make_migrations_renamed_from() {
+		let columns = extract_columns(&parse_quote!({
+			rowid: Option<i64>,
+			#[turbosql(renamed_from = "name")]
+			full_name: Option<String>,
+		}));
+		let table = Table {
+			ident: parse_quote!(Person),
+			span: Span::call_site(),
+			name: "person".to_string(),
+			columns,
+			unique_keys: vec![],
+			indexes: vec![],
+			db: None,
+			allow_rebuild: false,
+			checks: vec![],
+		};
+		let rename = "ALTER TABLE person RENAME COLUMN name TO full_name".to_string();
+
+		// new table: no old column to rename
+		let migrations = make_migrations(&table, &[]);
+		assert_eq!(migrations[1], "ALTER TABLE person ADD COLUMN full_name TEXT");
+
+		// old column exists: rename it
+		let existing = vec!["ALTER TABLE person ADD COLUMN name TEXT".to_string()];
+		assert_eq!(make_migrations(&table, &existing)[1], rename);
+
+		// already renamed: same migration, so it is deduplicated
+		let existing = vec![existing[0].clone(), rename.clone()];
+		assert_eq!(make_migrations(&table, &existing)[1], rename);
+
+		// column was added under its new name before renamed_from was added: keep adding it
+		let existing = vec![
+			"ALTER TABLE person ADD COLUMN name TEXT".to_string(),
+			"ALTER TABLE person ADD COLUMN full_name TEXT".to_string(),
+		];
+		assert_eq!(make_migrations(&table, &existing)[1], "ALTER TABLE person ADD COLUMN full_name TEXT");
+	}
+
+	#[test]
+	fn test_rebuild_migrations() {
+		let columns = extract_columns(&parse_quote!({
+			rowid: Option<i64>,
+			age: i64,
+			name: Option<String>,
+		}));
+		let table = Table {
+			ident: parse_quote!(Person),
+			span: Span::call_site(),
+			name: "person".to_string(),
+			columns,
+			unique_keys: vec![vec!["name".to_string()]],
+			indexes: vec![],
+			db: None,
+			allow_rebuild: true,
+			checks: vec![],
+		};
+		let existing = vec![
+			"CREATE TABLE person (rowid INTEGER PRIMARY KEY) STRICT".to_string(),
+			"ALTER TABLE person ADD COLUMN age TEXT".to_string(),
+			"ALTER TABLE person ADD COLUMN name TEXT".to_string(),
+			"ALTER TABLE person ADD COLUMN removed BLOB".to_string(),
+		];
+		let previous = ["age", "name", "removed"]
+			.iter()
+			.zip(["TEXT", "TEXT", "BLOB"])
+			.map(|(name, sql_type)| MiniColumn {
+				name: name.to_string(),
+				rust_type: String::new(),
+				sql_type: sql_type.to_string(),
+				references: None,
+				checks: vec![],
+				declared_sql_type: false,
+				native_text: false,
+			})
+			.collect::<Vec<_>>();
+
+		let (changed, removed) = rebuild::incompatible_changes(&table, &previous);
+		assert_eq!(
+			changed.iter().map(|(c, old)| (c.name.as_str(), old.as_str())).collect::<Vec<_>>(),
+			[("age", "TEXT")]
+		);
+		assert_eq!(removed, ["removed"]);
+
+		let migrations = rebuild::rebuild_migrations(&table, &previous, &existing);
+		assert_eq!(migrations[0], "CREATE TABLE _turbosql_rebuild_1_person (rowid INTEGER PRIMARY KEY, age INTEGER NOT NULL DEFAULT 0, name TEXT) STRICT");
+		assert!(column_exists(&migrations, "person", "age"));
+		assert!(!column_exists(&migrations, "person", "removed"));
+		assert!(!column_exists(&migrations, "son", "age"));
+
+		let conn = rusqlite::Connection::open_in_memory().unwrap();
+		for sql in &existing {
+			conn.execute(sql, params![]).unwrap();
+		}
+		conn
+			.execute(
+				"INSERT INTO person (age, name, removed) VALUES ('42', 'a', x''), (NULL, 'b', NULL)",
+				params![],
+			)
+			.unwrap();
+		let run = |conn: &rusqlite::Connection, migrations: &[String]| -> Option<String> {
+			for sql in migrations {
+				if sql.starts_with("SELECT ") {
+					if let Some(problem) = conn.query_row(sql, params![], |row| row.get(0)).unwrap() {
+						return Some(problem);
+					}
+				} else {
+					conn.execute(sql, params![]).unwrap();
+				}
+			}
+			None
+		};
+
+		// a value that doesn't convert stops the rebuild before the old table is dropped
+		conn.execute("INSERT INTO person (age) VALUES ('abc')", params![]).unwrap();
+		assert_eq!(
+			run(&conn, &migrations).as_deref(),
+			Some("person.age: 1 values can't be converted to INTEGER")
+		);
+		conn
+			.execute_batch("DELETE FROM person WHERE age = 'abc'; DROP TABLE _turbosql_rebuild_1_person")
+			.unwrap();
+
+		assert_eq!(run(&conn, &migrations), None);
+		let rows = conn
+			.prepare("SELECT rowid, age, name FROM person ORDER BY rowid")
+			.unwrap()
+			.query_map(params![], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+			.unwrap()
+			.collect::<rusqlite::Result<Vec<(i64, i64, String)>>>()
+			.unwrap();
+		assert_eq!(rows, [(1, 42, "a".to_string()), (2, 0, "b".to_string())]);
+
+		// the unique index survives, and a second rebuild gets its own statements
+		let existing = [existing, migrations].concat();
+		let migrations = rebuild::rebuild_migrations(&table, &previous, &existing);
+		assert_eq!(migrations[3], "/* rebuild 2 */ DROP TABLE person");
+		assert_eq!(
+			migrations[5],
+			"/* rebuild 2 */ CREATE UNIQUE INDEX IF NOT EXISTS person_name_unique ON person (name)"
+		);
+	}
+
+	#[test]
+	fn test_verify_conversion() {
+		let columns = extract_columns(&parse_quote!({
+			rowid: Option<i64>,
+			name: Option<std::string::String>,
+		}));
+		let table = Table {
+			ident: parse_quote!(Person),
+			span: Span::call_site(),
+			name: "person".to_string(),
+			columns,
+			unique_keys: vec![],
+			indexes: vec![],
+			db: None,
+			allow_rebuild: false,
+			checks: vec![],
+		};
+		let c = &table.columns[1];
+		let previous = MiniColumn {
+			name: "name".to_string(),
+			rust_type: c.rust_type.clone(),
+			sql_type: "TEXT".to_string(),
+			references: None,
+			checks: vec![],
+			declared_sql_type: false,
+			native_text: false,
+		};
+		let value = rebuild::conversion(&previous, c).unwrap();
+		let check = rebuild::verify_conversion(&table, &previous, c, &value);
+
+		let conn = rusqlite::Connection::open_in_memory().unwrap();
+		conn
+			.execute_batch(
+				"CREATE TABLE person (rowid INTEGER PRIMARY KEY, name TEXT) STRICT;
+				INSERT INTO person (name) VALUES ('\"a\"'), ('null'), (NULL), ('plain'), ('[2023,318]');",
+			)
+			.unwrap();
+		let problem = |conn: &rusqlite::Connection| -> Option<String> {
+			conn.query_row(&check, params![], |row| row.get(0)).unwrap()
+		};
+
+		// JSON that isn't a string, like a `time` date serialized as an array, stops the conversion
+		assert_eq!(problem(&conn).as_deref(), Some("person.name: 1 values can't be converted to TEXT"));
+		conn.execute("DELETE FROM person WHERE rowid = 5", params![]).unwrap();
+
+		assert_eq!(problem(&conn), None);
+		conn.execute(&format!("UPDATE person SET name = {}", value), params![]).unwrap();
+		let names = conn
+			.prepare("SELECT name FROM person ORDER BY rowid")
+			.unwrap()
+			.query_map(params![], |row| row.get(0))
+			.unwrap()
+			.collect::<rusqlite::Result<Vec<Option<String>>>>()
+			.unwrap();
+		assert_eq!(names, [Some("a".to_string()), None, None, Some("plain".to_string())]);
+	}
+
+	#[test]
+	fn test_struct_checks() {
+		assert_eq!(
+			sql_identifiers("\"Max Age\" > x'0F' -- note\n AND [a] = 'b''c' /* d */ AND 1e5 < e"),
+			["Max Age", "AND", "a", "AND", "e"]
+		);
+
+		let columns = extract_columns(&parse_quote!({
+			rowid: Option<i64>,
+			min_age: Option<i64>,
+			max_age: Option<i64>,
+			note: Option<String>,
+			extra: Option<i64>,
+		}));
+		assert_eq!(check_column(&parse_quote!("min_age <= 1e3 OR max_age = 'note'"), &columns), 2);
+
+		let mut table = Table {
+			ident: parse_quote!(Person),
+			span: Span::call_site(),
+			name: "person".to_string(),
+			columns: columns[..4].to_vec(),
+			unique_keys: vec![],
+			indexes: vec![],
+			db: None,
+			allow_rebuild: false,
+			checks: vec!["min_age <= max_age".to_string()],
+		};
+
+		// a new table is created with its columns, so checks are table constraints
+		let existing = make_migrations(&table, &[]);
+		assert_eq!(
+			existing,
+			[
+				r#"CREATE TABLE person (rowid INTEGER PRIMARY KEY, min_age INTEGER, max_age INTEGER, note TEXT, CONSTRAINT "person: min_age <= max_age" CHECK (min_age <= max_age)) STRICT"#
+			]
+		);
+		assert!(column_exists(&existing, "person", "note"));
+		assert!(!column_exists(&existing, "person", "CHECK"));
+
+		// later columns are added as usual
+		table.columns = columns;
+		assert_eq!(
+			make_migrations(&table, &existing),
+			[existing[0].clone(), "ALTER TABLE person ADD COLUMN extra INTEGER".to_string()]
+		);
+	}
+
+	#[test]
+	fn test_convert_storage() {
+		// columns from a migrations.toml written before native storage, without native_text, were JSON
+		#[cfg(feature = "chrono")]
+		{
+			assert_eq!(
+				codec::Codec::of("TEXT", "Option < DateTime < Utc > >", false, false),
+				codec::Codec::Json
+			);
+			assert_eq!(
+				codec::Codec::of("TEXT", "Option < DateTime < Utc > >", false, true),
+				codec::Codec::Native
+			);
+		}
+		assert_eq!(codec::Codec::of("TEXT", "Option < String >", false, false), codec::Codec::Native);
+		assert_eq!(codec::Codec::of("TEXT", "Option < Box < str > >", false, false), codec::Codec::Json);
+		assert_eq!(codec::Codec::of("TEXT", "Option < Box < str > >", true, false), codec::Codec::Native);
+
+		let conn = rusqlite::Connection::open_in_memory().unwrap();
+		conn
+			.execute_batch(
+				"CREATE TABLE t (rowid INTEGER PRIMARY KEY, at TEXT) STRICT;
+				INSERT INTO t (at) VALUES ('\"2023-11-14T22:13:20.123Z\"'), (NULL);",
+			)
+			.unwrap();
+		let values = |conn: &rusqlite::Connection| {
+			conn
+				.prepare("SELECT at FROM t ORDER BY rowid")
+				.unwrap()
+				.query_map(params![], |row| row.get(0))
+				.unwrap()
+				.collect::<rusqlite::Result<Vec<Option<String>>>>()
+				.unwrap()
+		};
+
+		let to_native =
+			codec::Codec::Native.convert_from(codec::Codec::Json, "DateTime < Utc >", "at").unwrap();
+		conn.execute(&format!("UPDATE t SET at = {}", to_native), params![]).unwrap();
+		assert_eq!(values(&conn), [Some("2023-11-14T22:13:20.123Z".to_string()), None]);
+
+		let to_json =
+			codec::Codec::Json.convert_from(codec::Codec::Native, "DateTime < Utc >", "at").unwrap();
+		conn.execute(&format!("UPDATE t SET at = {}", to_json), params![]).unwrap();
+		assert_eq!(values(&conn), [Some("\"2023-11-14T22:13:20.123Z\"".to_string()), None]);
+
+		// UUIDs move between JSON strings, hyphenated text and 16-byte blobs
+		#[cfg(feature = "uuid")]
+		{
+			use codec::Codec::{Json, Native, UuidText};
+			use rusqlite::types::Value;
+			let convert = |from: codec::Codec, to: codec::Codec, value: Value| -> Value {
+				let sql = format!(
+					"SELECT {} FROM (SELECT ? AS at)",
+					to.convert_from(from, "Option < Uuid >", "at").unwrap()
+				);
+				conn.query_row(&sql, params![value], |row| row.get(0)).unwrap()
+			};
+			let json = Value::Text("\"01234567-89ab-cdef-0123-456789abcdef\"".into());
+			let text = Value::Text("01234567-89ab-cdef-0123-456789abcdef".into());
+			let blob =
+				Value::Blob(vec![1, 35, 69, 103, 137, 171, 205, 239, 1, 35, 69, 103, 137, 171, 205, 239]);
+
+			assert_eq!(convert(Json, Native, json.clone()), blob);
+			assert_eq!(convert(Native, Json, blob.clone()), json);
+			assert_eq!(convert(UuidText, Native, text.clone()), blob);
+			assert_eq!(convert(Native, UuidText, blob), text);
+			assert_eq!(convert(Json, UuidText, json.clone()), text);
+			assert_eq!(convert(UuidText, Json, text), json);
+			assert_eq!(convert(Json, Native, Value::Null), Value::Null);
+			assert_eq!(convert(Json, Native, Value::Text("\"not a uuid\"".into())), Value::Null);
+		}
+	}
 }
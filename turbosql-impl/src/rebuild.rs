@@ -0,0 +1,253 @@
+use super::{codec, Column, MiniColumn, MiniTable, Table};
+
+/// Columns of `previous` as they are in the database, after any pending `renamed_from` renames.
+pub(super) fn previous_columns(table: &Table, previous: &MiniTable) -> Vec<MiniColumn> {
+	previous
+		.columns
+		.iter()
+		.map(|p| {
+			let renamed = table.columns.iter().find(|c| c.renamed_from.as_ref() == Some(&p.name));
+			MiniColumn { name: renamed.map_or(p.name.clone(), |c| c.name.clone()), ..p.clone() }
+		})
+		.collect()
+}
+
+/// Whether a column from the last compile has the same type and constraints as `column`.
+pub(super) fn same_definition(previous: &MiniColumn, column: &Column) -> bool {
+	previous.sql_type == column.sql_type
+		&& previous.references == column.references
+		&& previous.checks == column.checks
+}
+
+/// Columns whose type or constraints changed, with their previous definition, and names of removed columns. `ALTER TABLE` can make neither change, so they need a table rebuild.
+pub(super) fn incompatible_changes<'a>(
+	table: &'a Table,
+	previous: &[MiniColumn],
+) -> (Vec<(&'a Column, String)>, Vec<String>) {
+	let changed = table
+		.columns
+		.iter()
+		.filter_map(|c| {
+			let p = previous.iter().find(|p| p.name == c.name)?;
+			(!same_definition(p, c)).then(|| (c, p.sql_definition(&table.name)))
+		})
+		.collect();
+
+	let removed = previous
+		.iter()
+		.filter(|p| !table.columns.iter().any(|c| c.name == p.name))
+		.map(|p| p.name.clone())
+		.collect();
+
+	(changed, removed)
+}
+
+/// SQL expression converting the values of a field whose type is unchanged, but that is stored differently than in `previous`.
+pub(super) fn conversion(previous: &MiniColumn, column: &Column) -> Option<String> {
+	(codec::base_type(&previous.rust_type) == codec::base_type(&column.rust_type))
+		.then(|| column.codec().convert_from(previous.codec(), &column.rust_type, &column.name))
+		.flatten()
+}
+
+/// Column names created by `m`, if it is the `CREATE TABLE` statement of `table` or of a rebuild of it.
+pub(super) fn created_columns<'a>(
+	m: &'a str,
+	table: &str,
+) -> Option<impl Iterator<Item = &'a str>> {
+	let (name, columns) = m.strip_prefix("CREATE TABLE ")?.split_once(" (")?;
+	let name = match name.strip_prefix("_turbosql_rebuild_") {
+		Some(name) => name.trim_start_matches(|c: char| c.is_ascii_digit()).strip_prefix('_')?,
+		None => name,
+	};
+	(name == table).then(|| {
+		split_definitions(columns)
+			.into_iter()
+			.filter_map(|c| c.split(' ').next())
+			.filter(|c| !c.eq_ignore_ascii_case("CHECK") && !c.eq_ignore_ascii_case("CONSTRAINT"))
+	})
+}
+
+/// Split the definitions in a `CREATE TABLE` at the commas that aren't inside parentheses or quotes.
+fn split_definitions(columns: &str) -> Vec<&str> {
+	let mut definitions = Vec::new();
+	let (mut depth, mut quote, mut start, mut end) = (0, None, 0, columns.len());
+	for (i, c) in columns.char_indices() {
+		match (quote, c) {
+			(Some(q), c) if c == q => quote = None,
+			(Some(_), _) => (),
+			(None, '\'' | '"' | '`') => quote = Some(c),
+			(None, '(') => depth += 1,
+			(None, ')') if depth == 0 => {
+				end = i;
+				break;
+			}
+			(None, ')') => depth -= 1,
+			(None, ',') if depth == 0 => {
+				definitions.push(columns[start..i].trim());
+				start = i + 1;
+			}
+			_ => (),
+		}
+	}
+	definitions.push(columns[start..end].trim());
+	definitions
+}
+
+/// Definitions of all of `table`'s columns, followed by its struct-level checks as table constraints, as used in `CREATE TABLE`.
+pub(super) fn column_defs(table: &Table) -> String {
+	table
+		.columns
+		.iter()
+		.map(|c| match &c.sql_default {
+			Some(sql_default) => {
+				format!("{} {} DEFAULT {}", c.name, c.sql_definition(&table.name), sql_default)
+			}
+			None => format!("{} {}", c.name, c.sql_definition(&table.name)),
+		})
+		.chain(table.checks.iter().map(|check| super::check_constraint(&table.name, check)))
+		.collect::<Vec<_>>()
+		.join(", ")
+}
+
+/// Whether `table`'s struct-level checks differ from the table constraints it was last compiled with, which needs a table rebuild.
+pub(super) fn checks_changed(table: &Table, previous: &MiniTable) -> bool {
+	let mut checks = table.checks.clone();
+	let mut previous_checks = previous.checks.clone();
+	checks.sort();
+	previous_checks.sort();
+	checks != previous_checks
+}
+
+/// A query that returns why the copy into `new_table` lost data, or NULL. Turbosql runs read-only migrations as checks, and rolls back if the value isn't NULL.
+///
+/// A converted value is lost if converting it back to its previous storage class doesn't give the original, like `'abc'` becoming `0`.
+fn verify_copy(table: &Table, previous: &[MiniColumn], new_table: &str) -> String {
+	let mut problems = vec![format!(
+		"CASE WHEN (SELECT COUNT(*) FROM {old}) != (SELECT COUNT(*) FROM {new}) THEN '{table}: rows were lost' END",
+		old = table.name,
+		new = new_table,
+		table = table.name,
+	)];
+
+	problems.extend(table.columns.iter().filter_map(|c| {
+		let p = previous.iter().find(|p| p.name == c.name)?;
+		let converted = conversion(p, c).is_some();
+		(converted || p.sql_type != c.sql_type).then_some((c, p, converted))
+	}).map(|(c, p, converted)| {
+		let affinity = c.sql_type.split(' ').next().unwrap();
+		let round_trip = ["integer", "real", "text", "blob"]
+			.iter()
+			.map(|class| format!("WHEN '{}' THEN CAST(n.{} AS {})", class, c.name, class.to_uppercase()))
+			.collect::<Vec<_>>()
+			.join(" ");
+		// a converted value is lost if it became NULL, and a cast value if casting it back changes it
+		let lost = match converted {
+			true => lost(p, &format!("o.{}", c.name), &format!("n.{}", c.name)),
+			false => format!("CASE typeof(o.{column}) {round_trip} END IS NOT o.{column}", column = c.name),
+		};
+		format!(
+			"(SELECT '{table}.{column}: ' || COUNT(*) || ' values can''t be converted to {affinity}' FROM {table} o JOIN {new} n ON n.rowid = o.rowid WHERE {lost} HAVING COUNT(*) > 0)",
+			table = table.name,
+			column = c.name,
+			new = new_table,
+		)
+	}));
+
+	// COALESCE needs at least two arguments
+	format!("SELECT COALESCE({}, NULL)", problems.join(", "))
+}
+
+/// A query that returns why converting `column` in place to `value` would lose data, or NULL. It runs before the `UPDATE`, while the previous values are still there.
+pub(super) fn verify_conversion(
+	table: &Table,
+	previous: &MiniColumn,
+	column: &Column,
+	value: &str,
+) -> String {
+	format!(
+		"SELECT COALESCE((SELECT '{table}.{column}: ' || COUNT(*) || ' values can''t be converted to {affinity}' FROM {table} WHERE {lost} HAVING COUNT(*) > 0), NULL)",
+		table = table.name,
+		column = column.name,
+		affinity = column.sql_type.split(' ').next().unwrap(),
+		lost = lost(previous, &column.name, &format!("({})", value)),
+	)
+}
+
+/// Whether the non-NULL value `old` of a column stored like `previous` was lost by converting it to `new`, which is NULL. A JSON `null` is a NULL already.
+fn lost(previous: &MiniColumn, old: &str, new: &str) -> String {
+	let lost = format!("{old} IS NOT NULL AND {new} IS NULL");
+	match previous.codec() {
+		codec::Codec::Json => {
+			format!("{lost} AND CASE WHEN json_valid({old}) THEN json_type({old}) END IS NOT 'null'")
+		}
+		_ => lost,
+	}
+}
+
+/// SQLite's table rebuild procedure (https://www.sqlite.org/lang_altertable.html#otheralter): create the new table, copy the data, check that none was lost, drop the old table, rename the new one, and recreate indexes.
+///
+/// Every statement is unique to this rebuild, so later rebuilds of the same table aren't deduplicated away.
+pub(super) fn rebuild_migrations(
+	table: &Table,
+	previous: &[MiniColumn],
+	existing: &[String],
+) -> Vec<String> {
+	let n = 1
+		+ existing
+			.iter()
+			.filter(|m| {
+				m.starts_with("ALTER TABLE _turbosql_rebuild_")
+					&& m.ends_with(&format!(" RENAME TO {}", table.name))
+			})
+			.count();
+	let new_table = format!("_turbosql_rebuild_{}_{}", n, table.name);
+
+	let column_defs = column_defs(table);
+
+	let create = if cfg!(feature = "sqlite-compat-no-strict-tables") {
+		format!("CREATE TABLE {} ({})", new_table, column_defs)
+	} else {
+		format!("CREATE TABLE {} ({}) STRICT", new_table, column_defs)
+	};
+
+	// copy the columns that already exist, converting any whose type or storage changed
+
+	let (names, values): (Vec<_>, Vec<_>) = table
+		.columns
+		.iter()
+		.filter_map(|c| {
+			let p = previous.iter().find(|p| p.name == c.name)?;
+			let value = match conversion(p, c) {
+				Some(value) => value,
+				None if p.sql_type == c.sql_type => return Some((c.name.clone(), c.name.clone())),
+				None => format!("CAST({} AS {})", c.name, c.sql_type.split(' ').next().unwrap()),
+			};
+			let value = match (c.sql_type.ends_with("NOT NULL"), &c.sql_default) {
+				(true, Some(sql_default)) => format!("COALESCE({}, {})", value, sql_default),
+				_ => value,
+			};
+			Some((c.name.clone(), value))
+		})
+		.unzip();
+
+	let copy = format!(
+		"INSERT INTO {} ({}) SELECT {} FROM {}",
+		new_table,
+		names.join(", "),
+		values.join(", "),
+		table.name
+	);
+
+	let mut migrations = vec![
+		create,
+		copy,
+		verify_copy(table, previous, &new_table),
+		format!("/* rebuild {} */ DROP TABLE {}", n, table.name),
+		format!("ALTER TABLE {} RENAME TO {}", new_table, table.name),
+	];
+
+	migrations
+		.extend(super::make_indexes(table).into_iter().map(|sql| format!("/* rebuild {} */ {}", n, sql)));
+
+	migrations
+}
@@ -0,0 +1,71 @@
+use super::Table;
+use quote::{quote, quote_spanned};
+
+/// SELECT rowid, name1, name2... FROM tablename WHERE rowid = ?
+pub(super) fn select(table: &Table, has_skipped_fields: bool) -> proc_macro2::TokenStream {
+	let column_names = table.columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>().join(", ");
+
+	let sql_get = format!("SELECT {} FROM {} WHERE rowid = ?", column_names, table.name);
+	let sql_all = format!("SELECT {} FROM {}", column_names, table.name);
+	let sql_count = format!("SELECT COUNT(*) FROM {}", table.name);
+	let sql_exists = format!("SELECT EXISTS(SELECT 1 FROM {} WHERE rowid = ?)", table.name);
+
+	for sql in [&sql_get, &sql_all, &sql_count, &sql_exists] {
+		super::validate_sql_or_abort(table.db.as_deref(), sql);
+	}
+
+	let row_casters = table.columns.iter().enumerate().map(|(i, c)| {
+		let ident = &c.ident;
+		let value = c.codec().read(c.span, i, &quote!(Self), ident);
+		quote_spanned!(c.span => #ident: #value)
+	});
+
+	let rest = has_skipped_fields.then(|| quote!(..Default::default()));
+
+	let handle_row = quote! {
+		|row: &::turbosql::rusqlite::Row| -> Result<Self, ::turbosql::Error> {
+			Ok(Self {
+				#(#row_casters,)*
+				#rest
+			})
+		}
+	};
+
+	let db_handle = table.db_handle();
+
+	quote_spanned! { table.span =>
+		fn get(rowid: i64) -> Result<Option<Self>, ::turbosql::Error> {
+			#db_handle.with(|db| {
+				let db = db.borrow();
+				let mut stmt = db.prepare_cached(#sql_get)?;
+				let mut rows = stmt.query_and_then([rowid], #handle_row)?;
+				rows.next().transpose()
+			})
+		}
+
+		fn all() -> Result<Vec<Self>, ::turbosql::Error> {
+			#db_handle.with(|db| {
+				let db = db.borrow();
+				let mut stmt = db.prepare_cached(#sql_all)?;
+				let rows = stmt.query_and_then([], #handle_row)?;
+				rows.collect()
+			})
+		}
+
+		fn count() -> Result<i64, ::turbosql::Error> {
+			#db_handle.with(|db| {
+				let db = db.borrow();
+				let mut stmt = db.prepare_cached(#sql_count)?;
+				Ok(stmt.query_row([], |row| row.get(0))?)
+			})
+		}
+
+		fn exists(rowid: i64) -> Result<bool, ::turbosql::Error> {
+			#db_handle.with(|db| {
+				let db = db.borrow();
+				let mut stmt = db.prepare_cached(#sql_exists)?;
+				Ok(stmt.query_row([rowid], |row| row.get(0))?)
+			})
+		}
+	}
+}
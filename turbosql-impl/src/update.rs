@@ -1,6 +1,6 @@
 use super::Table;
 use proc_macro_error::abort_call_site;
-use quote::quote_spanned;
+use quote::{quote, quote_spanned};
 
 /// UPDATE tablename SET name1=?, name2=?... WHERE rowid=?
 pub(super) fn update(table: &Table) -> proc_macro2::TokenStream {
@@ -10,34 +10,39 @@ pub(super) fn update(table: &Table) -> proc_macro2::TokenStream {
 
 	let sql = makesql_update(table);
 
-	super::validate_sql_or_abort(&sql);
+	super::validate_sql_or_abort(table.db.as_deref(), &sql);
 
 	let mut columns = table.columns.clone();
 	columns.rotate_left(1);
-	let columns = columns.iter().map(|c| {
-		let ident = &c.ident;
-		if c.sql_type.starts_with("TEXT") && c.rust_type != "Option < String >" && c.rust_type != "String" {
-			quote_spanned!(c.span => &::turbosql::serde_json::to_string(&self.#ident)? as &dyn ::turbosql::ToSql)
-		} else {
-			quote_spanned!(c.span => &self.#ident as &dyn ::turbosql::ToSql)
-		}
-	}).collect::<Vec<_>>();
+	let columns = columns
+		.iter()
+		.map(|c| {
+			let ident = &c.ident;
+			c.codec().to_sql_dyn(c.span, quote!(self.#ident))
+		})
+		.collect::<Vec<_>>();
+
+	let db_handle = table.db_handle();
+	let transaction_fn = table.transaction_fn();
 
 	quote_spanned! { table.span =>
 		fn update(&self) -> Result<usize, ::turbosql::Error> {
 			assert!(self.rowid.is_some());
-			::turbosql::__TURBOSQL_DB.with(|db| {
-				let db = db.borrow_mut();
+			#db_handle.with(|db| {
+				let db = db.borrow();
 				let mut stmt = db.prepare_cached(#sql)?;
 				Ok(stmt.execute(&[#( #columns ),*] as &[&dyn ::turbosql::ToSql])?)
 			})
 		}
 
-		fn update_batch<T: AsRef<#table>>(rows: &[T]) -> Result<(), ::turbosql::Error>{
-			for row in rows {
-				row.as_ref().update()?;
-			}
-			Ok(())
+		fn update_batch<T: AsRef<#table>>(rows: &[T]) -> Result<usize, ::turbosql::Error> {
+			#transaction_fn(|| {
+				let mut updated = 0;
+				for row in rows {
+					updated += row.as_ref().update()?;
+				}
+				Ok(updated)
+			})
 		}
 	}
 }
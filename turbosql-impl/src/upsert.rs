@@ -1,3 +1,115 @@
-/// UPDATE tablename SET name1=?, name2=?... WHERE rowid=?
-/// 
-/// INSERT INTO tablename (name1, name2...) VALUES (?1, ?2...) ON CONFLICT rowid DO UPDATE SET name1=?, name2=?...
+use super::{insert::insert_params, Table};
+use proc_macro_error::abort_call_site;
+use quote::{format_ident, quote, quote_spanned};
+
+/// INSERT INTO tablename (rowid, name1, name2...) VALUES (?, ?, ?...) ON CONFLICT(rowid) DO UPDATE SET name1=excluded.name1, name2=excluded.name2... RETURNING rowid
+pub(super) fn upsert(table: &Table) -> proc_macro2::TokenStream {
+	if table.columns[0].name != "rowid" {
+		abort_call_site!("First field must be `rowid: Option<i64>`");
+	}
+
+	let sql = makesql_upsert(table);
+
+	super::validate_sql_or_abort(table.db.as_deref(), &sql);
+
+	let columns = insert_params(&table.columns, quote!(self));
+
+	let db_handle = table.db_handle();
+	let transaction_fn = table.transaction_fn();
+
+	quote_spanned! { table.span =>
+		fn upsert(&self) -> Result<i64, ::turbosql::Error> {
+			#db_handle.with(|db| {
+				let db = db.borrow();
+				let mut stmt = db.prepare_cached(#sql)?;
+				Ok(stmt.query_row(&[#( #columns ),*] as &[&dyn ::turbosql::ToSql], |row| row.get(0))?)
+			})
+		}
+
+		fn upsert_batch<T: AsRef<#table>>(rows: &[T]) -> Result<Vec<i64>, ::turbosql::Error> {
+			#transaction_fn(|| rows.iter().map(|row| row.as_ref().upsert()).collect())
+		}
+	}
+}
+
+/// INSERT INTO tablename (name1, name2...) VALUES (?, ?...) ON CONFLICT(key1, key2...) DO UPDATE SET name1=excluded.name1... RETURNING rowid
+///
+/// Generates one `upsert_on_<key>` method for each unique key declared with `#[turbosql(unique)]` or `#[turbosql(unique(...))]`.
+pub(super) fn upsert_on_unique_keys(table: &Table) -> Vec<proc_macro2::TokenStream> {
+	table
+		.unique_keys
+		.iter()
+		.map(|key| {
+			let sql = makesql_upsert_on(table, key);
+
+			super::validate_sql_or_abort(table.db.as_deref(), &sql);
+
+			let columns = insert_params(&table.columns[1..], quote!(self));
+
+			let fn_name = format_ident!("upsert_on_{}", key.join("_"));
+			let doc = format!(
+				"Inserts this row, or if a row with the same `{}` already exists, overwrites its other fields. `rowid` is ignored. On success, the `rowid` of the inserted or updated row is returned.",
+				key.join("`, `")
+			);
+
+			let db_handle = table.db_handle();
+
+			quote_spanned! { table.span =>
+				#[doc = #doc]
+				pub fn #fn_name(&self) -> Result<i64, ::turbosql::Error> {
+					#db_handle.with(|db| {
+						let db = db.borrow();
+						let mut stmt = db.prepare_cached(#sql)?;
+						Ok(stmt.query_row(&[#( #columns ),*] as &[&dyn ::turbosql::ToSql], |row| row.get(0))?)
+					})
+				}
+			}
+		})
+		.collect()
+}
+
+fn makesql_upsert_on(table: &Table, key: &[String]) -> String {
+	let columns = &table.columns[1..];
+
+	let mut sql = format!("INSERT INTO {} (", table.name);
+	sql += columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>().join(", ").as_str();
+	sql += ") VALUES (";
+	sql += columns.iter().map(|_| "?").collect::<Vec<_>>().join(", ").as_str();
+	sql += &format!(") ON CONFLICT({}) DO UPDATE SET ", key.join(", "));
+
+	let assignments = columns
+		.iter()
+		.filter(|c| !key.contains(&c.name))
+		.map(|c| format!("{0}=excluded.{0}", c.name))
+		.collect::<Vec<_>>();
+
+	// If every column is part of the key, there's nothing to overwrite, but RETURNING still needs a row.
+	sql += &match assignments.is_empty() {
+		true => format!("{0}=excluded.{0}", key[0]),
+		false => assignments.join(", "),
+	};
+	sql += " RETURNING rowid";
+
+	sql
+}
+
+fn makesql_upsert(table: &Table) -> String {
+	let mut sql = format!("INSERT INTO {} (", table.name);
+	sql += table.columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>().join(", ").as_str();
+	sql += ") VALUES (";
+	sql += table.columns.iter().map(|_| "?").collect::<Vec<_>>().join(", ").as_str();
+	sql += ") ON CONFLICT(rowid) DO UPDATE SET ";
+
+	// A table with only a rowid still needs something to SET for RETURNING to yield a row.
+	let assignments = match &table.columns[1..] {
+		[] => "rowid=excluded.rowid".to_string(),
+		columns => {
+			columns.iter().map(|c| format!("{0}=excluded.{0}", c.name)).collect::<Vec<_>>().join(", ")
+		}
+	};
+
+	sql += assignments.as_str();
+	sql += " RETURNING rowid";
+
+	sql
+}
@@ -1,7 +1,10 @@
 use rusqlite::{Connection, OpenFlags};
 use serde::Deserialize;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::sync::Mutex;
 
 // these re-exports are used in macro expansions
@@ -17,25 +20,224 @@ pub use rusqlite::{
 pub use serde::Serialize;
 #[doc(hidden)]
 pub use serde_json;
-pub use turbosql_impl::{execute, select, update, Turbosql};
+#[cfg(feature = "uuid")]
+#[doc(hidden)]
+pub use uuid;
+pub use turbosql_impl::{execute, select, update, Turbosql, TurbosqlEnum, TurbosqlType};
+
+mod turbosql_type;
+pub use turbosql_type::{TurbosqlEnum, TurbosqlType};
+#[doc(hidden)]
+pub use turbosql_type::{
+	check_sql_type, AutoBind, AutoRead, BindJson, BindTyped, EnumProbe, NoTurbosqlType, NotTurbosqlEnum, ReadJson,
+	ReadTyped, TypeProbe,
+};
+
+mod unix_ms;
+pub use unix_ms::{UnixMs, UnixMsTimestamp};
+mod u64_as;
+pub use u64_as::{U64BlobBe, U64Text};
+#[cfg(feature = "uuid")]
+mod uuid_text;
+#[cfg(feature = "uuid")]
+pub use uuid_text::UuidText;
+
+#[cfg(feature = "async")]
+mod worker;
+#[cfg(feature = "async")]
+pub use worker::{run_async, transaction_async, DbFuture, TurbosqlAsync};
 
 /// Wrapper for `Vec<u8>` that may one day impl `Read`, `Write` and `Seek` traits.
 pub type Blob = Vec<u8>;
 
+/// A reference to a row of another Turbosql table, stored as that row's `rowid`.
+///
+/// An `Option<Ref<Person>>` field generates an `INTEGER REFERENCES person(rowid)` column, so SQLite enforces that the referenced row exists. Add `#[turbosql(on_delete = "cascade")]` (or `"set null"`, `"restrict"`, ...) to choose what happens to this row when the referenced row is deleted.
+///
+/// ```rust
+/// # use turbosql::{Ref, Turbosql};
+/// # #[derive(Turbosql, Default)]
+/// # struct Person { rowid: Option<i64>, name: Option<String> }
+/// #[derive(Turbosql, Default)]
+/// struct Pet {
+///     rowid: Option<i64>,
+///     #[turbosql(on_delete = "cascade")]
+///     owner: Option<Ref<Person>>,
+/// }
+///
+/// let owner = Person { rowid: None, name: Some("Bob".into()) }.insert()?;
+/// let pet = Pet { rowid: None, owner: Some(Ref::new(owner)) };
+/// assert_eq!(pet.owner.unwrap().load()?.unwrap().name, Some("Bob".into()));
+/// # Ok::<(), turbosql::Error>(())
+/// ```
+pub struct Ref<T> {
+	rowid: i64,
+	table: PhantomData<fn() -> T>,
+}
+
+impl<T> Ref<T> {
+	/// A reference to the row with the given `rowid`.
+	pub fn new(rowid: i64) -> Self {
+		Ref { rowid, table: PhantomData }
+	}
+
+	/// The `rowid` of the referenced row.
+	pub fn rowid(&self) -> i64 {
+		self.rowid
+	}
+}
+
+impl<T: Turbosql> Ref<T> {
+	/// Fetches the referenced row, or `None` if it doesn't exist.
+	pub fn load(&self) -> Result<Option<T>, Error> {
+		T::get(self.rowid)
+	}
+}
+
+// manual impls, so they don't require T to implement these traits
+
+impl<T> Clone for Ref<T> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+
+impl<T> Copy for Ref<T> {}
+
+impl<T> PartialEq for Ref<T> {
+	fn eq(&self, other: &Self) -> bool {
+		self.rowid == other.rowid
+	}
+}
+
+impl<T> Eq for Ref<T> {}
+
+impl<T> std::hash::Hash for Ref<T> {
+	fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+		self.rowid.hash(state);
+	}
+}
+
+impl<T> std::fmt::Debug for Ref<T> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_tuple("Ref").field(&self.rowid).finish()
+	}
+}
+
+impl<T> From<i64> for Ref<T> {
+	fn from(rowid: i64) -> Self {
+		Ref::new(rowid)
+	}
+}
+
+impl<T> ToSql for Ref<T> {
+	fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+		self.rowid.to_sql()
+	}
+}
+
+impl<T> FromSql for Ref<T> {
+	fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+		i64::column_result(value).map(Ref::new)
+	}
+}
+
+impl<T> Serialize for Ref<T> {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		self.rowid.serialize(serializer)
+	}
+}
+
+impl<'de, T> Deserialize<'de> for Ref<T> {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		i64::deserialize(deserializer).map(Ref::new)
+	}
+}
+
 /// `#[derive(Turbosql)]` generates impls for this trait.
 pub trait Turbosql {
 	/// Insert this row into the database. `rowid` must be `None`. On success, the new `rowid` is returned.
 	fn insert(&self) -> Result<i64, Error>;
 	/// Insert this row into the database, and update the `rowid` of the struct to match the new rowid in the database. `rowid` must be `None` on call. On success, the new `rowid` is returned.
 	fn insert_mut(&mut self) -> Result<i64, Error>;
-	/// Insert all rows in the slice into the database. All `rowid`s must be `None`. On success, returns `Ok(())`.
-	fn insert_batch<T: AsRef<Self>>(rows: &[T]) -> Result<(), Error>;
+	/// Insert all rows in the slice into the database in a single transaction, using multi-row `INSERT` statements. All `rowid`s must be `None`. On success, the new `rowid`s are returned in the same order as `rows`. If any row fails, no rows are inserted.
+	fn insert_batch<T: AsRef<Self>>(rows: &[T]) -> Result<Vec<i64>, Error>;
 	/// Updates this existing row in the database, based on `rowid`, which must be `Some`. All fields are overwritten in the database. On success, returns the number of rows updated, which should be 1.
 	fn update(&self) -> Result<usize, Error>;
-	/// Updates all rows in the slice in the database, based on `rowid`, which must be `Some`. All fields are overwritten in the database. On success, returns `Ok(())`.
-	fn update_batch<T: AsRef<Self>>(rows: &[T]) -> Result<(), Error>;
+	/// Updates all rows in the slice in the database in a single transaction, based on `rowid`, which must be `Some`. All fields are overwritten in the database. On success, returns the total number of rows updated. If any row fails, no rows are updated.
+	fn update_batch<T: AsRef<Self>>(rows: &[T]) -> Result<usize, Error>;
+	/// Inserts this row into the database if `rowid` is `None` or no row with that `rowid` exists, otherwise overwrites all fields of the existing row. On success, the `rowid` of the inserted or updated row is returned.
+	fn upsert(&self) -> Result<i64, Error>;
+	/// Upserts all rows in the slice into the database in a single transaction, as with [`upsert`](Turbosql::upsert). On success, the `rowid`s are returned in the same order as `rows`. If any row fails, no rows are changed.
+	fn upsert_batch<T: AsRef<Self>>(rows: &[T]) -> Result<Vec<i64>, Error>;
 	/// Deletes this existing row in the database, based on `rowid`, which must be `Some`. On success, returns the number of rows deleted, which should be 1.
 	fn delete(&self) -> Result<usize, Error>;
+	/// Deletes the row with the given `rowid`. On success, returns the number of rows deleted, which is 1 if the row existed and 0 otherwise.
+	fn delete_by_rowid(rowid: i64) -> Result<usize, Error>;
+	/// Fetches the row with the given `rowid`, or `None` if no such row exists.
+	fn get(rowid: i64) -> Result<Option<Self>, Error>
+	where
+		Self: Sized;
+	/// Fetches all rows in the table.
+	fn all() -> Result<Vec<Self>, Error>
+	where
+		Self: Sized;
+	/// Returns the number of rows in the table.
+	fn count() -> Result<i64, Error>;
+	/// Returns `true` if a row with the given `rowid` exists.
+	fn exists(rowid: i64) -> Result<bool, Error>;
+}
+
+/// A query returned by `select!(Iter<T> ...)`, which streams rows from the database instead of collecting them into memory.
+///
+/// The query runs when [`with`](SelectIter::with) or [`for_each`](SelectIter::for_each) is called. Each row is converted as it is read, and conversion errors are reported per row. Other Turbosql calls may be made while iterating.
+///
+/// ```rust
+/// # use turbosql::{Turbosql, select};
+/// # #[derive(Turbosql, Default)]
+/// # struct Person { rowid: Option<i64>, name: Option<String>, age: Option<i64>, image_jpg: Option<Vec<u8>> }
+/// let mut total_age = 0;
+/// select!(Iter<Person> "WHERE age > ?", 21).for_each(|person| {
+///     total_age += person.age.unwrap_or_default();
+///     Ok(())
+/// })?;
+///
+/// let first_names = select!(Iter<Person.name>)
+///     .with(|rows| rows.take(10).collect::<Result<Vec<_>, _>>())??;
+/// # Ok::<(), turbosql::Error>(())
+/// ```
+#[must_use = "the query does not run until `with` or `for_each` is called"]
+pub struct SelectIter<T, F> {
+	query: F,
+	_row: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<T, F> SelectIter<T, F>
+where
+	F: FnOnce(&mut dyn FnMut(&mut dyn Iterator<Item = Result<T, Error>>)) -> Result<(), Error>,
+{
+	#[doc(hidden)]
+	pub fn new(query: F) -> Self {
+		Self { query, _row: std::marker::PhantomData }
+	}
+
+	/// Runs the query and passes an iterator over the resulting rows to `f`, returning its result.
+	pub fn with<R>(self, f: impl FnOnce(&mut dyn Iterator<Item = Result<T, Error>>) -> R) -> Result<R, Error> {
+		let mut f = Some(f);
+		let mut result = None;
+		(self.query)(&mut |rows| result = f.take().map(|f| f(rows)))?;
+		result.ok_or(Error::OtherError("SelectIter query did not produce rows"))
+	}
+
+	/// Runs the query and calls `f` on each row, stopping at the first error from either the row or `f`.
+	pub fn for_each(self, mut f: impl FnMut(T) -> Result<(), Error>) -> Result<(), Error> {
+		self.with(|rows| {
+			for row in rows {
+				f(row?)?;
+			}
+			Ok(())
+		})?
+	}
 }
 
 /// Error type returned by Turbosql.
@@ -43,13 +245,82 @@ pub trait Turbosql {
 pub enum Error {
 	/// Passthrough [`rusqlite::Error`]
 	#[error(transparent)]
-	Rusqlite(#[from] rusqlite::Error),
+	Rusqlite(rusqlite::Error),
 	/// Passthrough [`serde_json::Error`]
 	#[error(transparent)]
 	SerdeJson(#[from] serde_json::Error),
 	/// Turbosql-specific error
 	#[error("Turbosql Error: {0}")]
 	OtherError(&'static str),
+	/// The database file could not be opened or configured.
+	#[error("Unable to open database {path:?}: {source}")]
+	OpenFailed {
+		/// Path of the database.
+		path: PathBuf,
+		/// Underlying SQLite error.
+		source: rusqlite::Error,
+	},
+	/// A migration applied to the database differs from the one this binary has at the same position, so the schema can't be trusted.
+	#[error("Mismatch in Turbosql migrations for {path:?}: database has {applied:?}, binary expects {expected:?}")]
+	MigrationMismatch {
+		/// Path of the database.
+		path: PathBuf,
+		/// Migration that was applied to the database.
+		applied: String,
+		/// Migration this binary has at that position.
+		expected: String,
+	},
+	/// The database has more migrations applied than this binary knows about, most likely because a newer version of the program has used it.
+	#[error("Database {path:?} has {applied} migrations applied, but this binary only knows {known}; was it written by a newer version of this program?")]
+	DatabaseNewerThanBinary {
+		/// Path of the database.
+		path: PathBuf,
+		/// Number of migrations applied to the database.
+		applied: usize,
+		/// Number of migrations this binary has.
+		known: usize,
+	},
+	/// The database's tables don't match the schema in `migrations.toml`. Only returned with [`SchemaCheck::Error`].
+	#[error("Database {path:?} does not match the schema in migrations.toml:\n{}", .differences.join("\n"))]
+	SchemaMismatch {
+		/// Path of the database.
+		path: PathBuf,
+		/// Description of each difference.
+		differences: Vec<String>,
+	},
+	/// A migration would have lost data or left foreign keys pointing at missing rows, so none of the pending migrations were applied.
+	#[error("Migrating {path:?} was rolled back: {reason}")]
+	MigrationRejected {
+		/// Path of the database.
+		path: PathBuf,
+		/// What the migration would have broken.
+		reason: String,
+	},
+	/// A row violated a `CHECK` constraint declared with `#[turbosql(check = "...")]`.
+	#[error("CHECK constraint failed on table {table}: {constraint}")]
+	CheckViolation {
+		/// Table that declares the constraint.
+		table: String,
+		/// The constraint's SQL expression.
+		constraint: String,
+	},
+}
+
+impl From<rusqlite::Error> for Error {
+	fn from(e: rusqlite::Error) -> Self {
+		if let rusqlite::Error::SqliteFailure(
+			rusqlite::ffi::Error { extended_code: rusqlite::ffi::SQLITE_CONSTRAINT_CHECK, .. },
+			Some(message),
+		) = &e
+		{
+			// Turbosql names each CHECK constraint `table: check`, and SQLite reports the name
+			let name = message.strip_prefix("CHECK constraint failed: ").unwrap_or(message);
+			if let Some((table, constraint)) = name.split_once(": ") {
+				return Error::CheckViolation { table: table.to_string(), constraint: constraint.to_string() };
+			}
+		}
+		Error::Rusqlite(e)
+	}
 }
 
 #[allow(dead_code)]
@@ -57,15 +328,214 @@ pub enum Error {
 struct MigrationsToml {
 	migrations_append_only: Option<Vec<String>>,
 	output_generated_schema_for_your_information_do_not_edit: Option<String>,
+	output_generated_tables_do_not_edit: Option<BTreeMap<String, MiniTable>>,
+	databases: Option<BTreeMap<String, MigrationsToml>>,
+}
+
+#[derive(Clone, Debug, Deserialize, Default)]
+struct MiniTable {
+	columns: Vec<MiniColumn>,
+}
+
+#[derive(Clone, Debug, Deserialize, Default)]
+struct MiniColumn {
+	name: String,
+	sql_type: String,
 }
 
 #[derive(Clone, Debug, Default)]
 struct DbPath {
-	path: Option<PathBuf>,
+	config: Config,
 	opened: bool,
 }
 
 static __DB_PATH: Lazy<Mutex<DbPath>> = Lazy::new(Default::default);
+static NAMED_DB_PATHS: Lazy<Mutex<HashMap<String, DbPath>>> = Lazy::new(Default::default);
+
+fn with_db_path<R>(db: Option<&str>, f: impl FnOnce(&mut DbPath) -> R) -> R {
+	match db {
+		None => f(&mut __DB_PATH.lock().unwrap()),
+		Some(name) => f(NAMED_DB_PATHS.lock().unwrap().entry(name.to_string()).or_default()),
+	}
+}
+
+/// PRAGMAs run on every new connection, unless overridden with [`Config::pragma`].
+const DEFAULT_PRAGMAS: &[(&str, &str)] = &[
+	("auto_vacuum", "INCREMENTAL"),
+	("journal_mode", "WAL"),
+	("wal_autocheckpoint", "8000"),
+	("synchronous", "NORMAL"),
+	("foreign_keys", "ON"),
+];
+
+/// How to handle a database whose tables don't match the schema in `migrations.toml`. See [`Config::schema_check`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SchemaCheck {
+	/// Don't check the schema.
+	Off,
+	/// Log a warning listing the differences.
+	#[default]
+	Warn,
+	/// Fail to open the database with [`Error::SchemaMismatch`].
+	Error,
+}
+
+/// Database connection settings. Build one, then call [`Config::apply`] before any other usage of Turbosql.
+///
+/// The configuration is shared by every thread's connection, including ones opened later.
+///
+/// ```rust,no_run
+/// turbosql::Config::new()
+///     .path("/var/lib/myapp/data.sqlite")
+///     .busy_timeout(std::time::Duration::from_secs(10))
+///     .cache_size(-64_000)
+///     .pragma("synchronous", "FULL")
+///     .apply()?;
+/// # Ok::<(), turbosql::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct Config {
+	db: Option<String>,
+	path: Option<PathBuf>,
+	read_only: bool,
+	create: bool,
+	schema_check: SchemaCheck,
+	busy_timeout: std::time::Duration,
+	cache_size: Option<i64>,
+	pragmas: Vec<(String, String)>,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Config {
+			db: None,
+			path: None,
+			read_only: false,
+			create: true,
+			schema_check: SchemaCheck::default(),
+			busy_timeout: std::time::Duration::from_millis(3000),
+			cache_size: None,
+			pragmas: Vec::new(),
+		}
+	}
+}
+
+impl Config {
+	/// The default configuration: a database named after the executable in the platform's data directory, created if missing, with a 3 second busy timeout.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// The default configuration for the named database `db`, as declared with `#[turbosql(db = "...")]`. Its default path is next to the main database, with `-{db}` appended to the filename stem.
+	pub fn named(db: impl Into<String>) -> Self {
+		Config { db: Some(db.into()), ..Self::default() }
+	}
+
+	/// Local path and filename of the SQLite database.
+	pub fn path(mut self, path: impl AsRef<Path>) -> Self {
+		self.path = Some(path.as_ref().to_owned());
+		self
+	}
+
+	/// Open the database read-only. Migrations are not run, and any write returns an error.
+	pub fn read_only(mut self, read_only: bool) -> Self {
+		self.read_only = read_only;
+		self
+	}
+
+	/// Create the database file if it does not exist. Defaults to `true`; ignored when read-only.
+	pub fn create(mut self, create: bool) -> Self {
+		self.create = create;
+		self
+	}
+
+	/// How long to retry when the database is locked by another connection before returning an error. Defaults to 3 seconds.
+	pub fn busy_timeout(mut self, timeout: std::time::Duration) -> Self {
+		self.busy_timeout = timeout;
+		self
+	}
+
+	/// What to do when, after migrating, the database's tables don't match the schema in `migrations.toml`, for example because the database was edited by hand. Defaults to [`SchemaCheck::Warn`].
+	pub fn schema_check(mut self, schema_check: SchemaCheck) -> Self {
+		self.schema_check = schema_check;
+		self
+	}
+
+	/// Sets `PRAGMA cache_size`: a number of pages if positive, or a number of KiB if negative.
+	pub fn cache_size(mut self, cache_size: i64) -> Self {
+		self.cache_size = Some(cache_size);
+		self
+	}
+
+	/// Run `PRAGMA name=value` on every new connection, replacing Turbosql's default for that PRAGMA if it has one. Turbosql's defaults are `auto_vacuum=INCREMENTAL`, `journal_mode=WAL`, `wal_autocheckpoint=8000`, `synchronous=NORMAL` and `foreign_keys=ON`.
+	pub fn pragma(mut self, name: impl Into<String>, value: impl ToString) -> Self {
+		let name = name.into();
+		self.pragmas.retain(|(n, _)| !n.eq_ignore_ascii_case(&name));
+		self.pragmas.push((name, value.to_string()));
+		self
+	}
+
+	/// Use this configuration for all database connections.
+	///
+	/// Must be called before any usage of Turbosql macros or will return an error.
+	pub fn apply(self) -> Result<(), Error> {
+		with_db_path(self.db.clone().as_deref(), |db_path| {
+			if db_path.opened {
+				return Err(Error::OtherError("Trying to apply Config when DB is already opened"));
+			}
+
+			db_path.config = self;
+
+			Ok(())
+		})
+	}
+
+	fn open_flags(&self) -> OpenFlags {
+		// We are handling the mutex by being thread_local, so SQLite can be opened in no-mutex mode; see:
+		// https://www.mail-archive.com/sqlite-users@mailinglists.sqlite.org/msg112907.html
+		if self.read_only {
+			OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX
+		} else if self.create {
+			OpenFlags::SQLITE_OPEN_READ_WRITE
+				| OpenFlags::SQLITE_OPEN_CREATE
+				| OpenFlags::SQLITE_OPEN_NO_MUTEX
+		} else {
+			OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_NO_MUTEX
+		}
+	}
+
+	/// A new connection to the database at `self.path`, with this configuration's open flags, busy timeout and PRAGMAs.
+	fn connect(&self) -> Result<Connection, Error> {
+		let path = self.path.as_ref().unwrap();
+		let open_failed = |source| Error::OpenFailed { path: path.clone(), source };
+
+		let conn = Connection::open_with_flags(path, self.open_flags()).map_err(open_failed)?;
+
+		conn.busy_timeout(self.busy_timeout).map_err(open_failed)?;
+		conn.execute_batch(&self.pragmas_sql()).map_err(open_failed)?;
+
+		Ok(conn)
+	}
+
+	fn pragmas_sql(&self) -> String {
+		let defaults = DEFAULT_PRAGMAS
+			.iter()
+			.filter(|(name, _)| !self.pragmas.iter().any(|(n, _)| n.eq_ignore_ascii_case(name)))
+			.map(|(name, value)| (name.to_string(), value.to_string()));
+
+		let cache_size = self.cache_size.map(|size| ("cache_size".to_string(), size.to_string()));
+
+		defaults
+			.chain(cache_size)
+			.chain(self.pragmas.iter().cloned())
+			// changing these on a read-only connection would need a write
+			.filter(|(name, _)| {
+				!self.read_only || !["auto_vacuum", "journal_mode"].contains(&name.to_lowercase().as_str())
+			})
+			.map(|(name, value)| format!("PRAGMA {}={};\n", name, value))
+			.collect()
+	}
+}
 
 /// Convenience function that returns the current time as milliseconds since UNIX epoch.
 pub fn now_ms() -> i64 {
@@ -80,10 +550,23 @@ pub fn now_µs() -> i64 {
 /// Returns the path to the database.
 pub fn db_path() -> PathBuf {
 	__TURBOSQL_DB.with(|_| {});
-	__DB_PATH.lock().unwrap().path.clone().unwrap()
+	__DB_PATH.lock().unwrap().config.path.clone().unwrap()
+}
+
+/// The embedded `migrations.toml` section for database `db`, or the top level for the main database.
+fn embedded_migrations_toml(db: Option<&str>) -> MigrationsToml {
+	let toml_decoded = embedded_root_migrations_toml();
+
+	match db {
+		None => toml_decoded,
+		Some(name) => {
+			toml_decoded.databases.and_then(|mut dbs| dbs.remove(name)).unwrap_or_default()
+		}
+	}
 }
 
-fn run_migrations(conn: &mut Connection, path: &Path) {
+/// The whole embedded `migrations.toml`, including the sections of named databases.
+fn embedded_root_migrations_toml() -> MigrationsToml {
 	#[cfg(doc)]
 	// if these are what's run in doctests, could add a test struct here to scaffold one-liner tests
 	let toml_decoded: MigrationsToml = MigrationsToml::default();
@@ -95,88 +578,308 @@ fn run_migrations(conn: &mut Connection, path: &Path) {
 		toml::from_str(include_str!(concat!(env!("OUT_DIR"), "/migrations.toml")))
 			.expect("Unable to decode embedded migrations.toml");
 
-	let target_migrations = toml_decoded.migrations_append_only.unwrap_or_default();
+	toml_decoded
+}
+
+fn run_migrations(conn: &mut Connection, path: &Path, db: Option<&str>) -> Result<(), Error> {
+	// Foreign keys are enforced outside of migrations only, so a table rebuild's DROP TABLE doesn't delete or orphan referencing rows.
+	// This PRAGMA is a no-op inside a transaction, so it's set around it.
+	let foreign_keys: bool = conn.query_row("PRAGMA foreign_keys", params![], |row| row.get(0))?;
+
+	if foreign_keys {
+		conn.execute_batch("PRAGMA foreign_keys = OFF")?;
+	}
+
+	let result = apply_migrations(conn, path, db);
+
+	if foreign_keys {
+		conn.execute_batch("PRAGMA foreign_keys = ON")?;
+	}
+
+	result
+}
+
+fn apply_migrations(conn: &mut Connection, path: &Path, db: Option<&str>) -> Result<(), Error> {
+	let target_migrations = embedded_migrations_toml(db).migrations_append_only.unwrap_or_default();
 
 	// filter out comments
 	let target_migrations: Vec<_> =
 		target_migrations.into_iter().filter(|m| !m.starts_with("--")).collect();
 
-	conn.execute("BEGIN EXCLUSIVE TRANSACTION", params![]).unwrap();
+	// rolls back if dropped on an error
+	let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Exclusive)?;
 
-	let _ = conn.execute("ALTER TABLE turbosql_migrations RENAME TO _turbosql_migrations", params![]);
+	let _ = tx.execute("ALTER TABLE turbosql_migrations RENAME TO _turbosql_migrations", params![]);
 
-	let result = conn.query_row(
+	let result = tx.query_row(
 		"SELECT sql FROM sqlite_master WHERE name = ?",
 		params!["_turbosql_migrations"],
-		|row| {
-			let sql: String = row.get(0).unwrap();
-			Ok(sql)
-		},
+		|row| row.get::<_, String>(0),
 	);
 
 	match result {
 		Err(rusqlite::Error::QueryReturnedNoRows) => {
 			// no migrations table exists yet, create
-			conn
-				.execute_batch(if cfg!(feature = "sqlite-compat-no-strict-tables") {
-					r#"CREATE TABLE _turbosql_migrations (rowid INTEGER PRIMARY KEY, migration TEXT NOT NULL)"#
-				} else {
-					r#"CREATE TABLE _turbosql_migrations (rowid INTEGER PRIMARY KEY, migration TEXT NOT NULL) STRICT"#
-				})
-				.expect("CREATE TABLE _turbosql_migrations");
-		}
-		Err(err) => {
-			panic!("Could not query sqlite_master table: {}", err);
+			tx.execute_batch(if cfg!(feature = "sqlite-compat-no-strict-tables") {
+				r#"CREATE TABLE _turbosql_migrations (rowid INTEGER PRIMARY KEY, migration TEXT NOT NULL)"#
+			} else {
+				r#"CREATE TABLE _turbosql_migrations (rowid INTEGER PRIMARY KEY, migration TEXT NOT NULL) STRICT"#
+			})?;
 		}
+		Err(err) => return Err(err.into()),
 		Ok(_) => (),
 	}
 
-	let applied_migrations = conn
-		.prepare("SELECT migration FROM _turbosql_migrations ORDER BY rowid")
-		.unwrap()
-		.query_map(params![], |row| Ok(row.get(0).unwrap()))
-		.unwrap()
-		.map(|x: Result<String, _>| x.unwrap())
+	let applied_migrations = tx
+		.prepare("SELECT migration FROM _turbosql_migrations ORDER BY rowid")?
+		.query_map(params![], |row| row.get(0))?
+		.collect::<Result<Vec<String>, _>>()?
+		.into_iter()
 		.filter(|m| !m.starts_with("--"))
 		.collect::<Vec<String>>();
 
 	// execute migrations
 
+	if applied_migrations.len() > target_migrations.len() {
+		return Err(Error::DatabaseNewerThanBinary {
+			path: path.to_owned(),
+			applied: applied_migrations.len(),
+			known: target_migrations.len(),
+		});
+	}
+
 	let mut a = applied_migrations.iter();
 	let mut t = target_migrations.iter();
+	let mut migrated = false;
 
 	loop {
 		match (a.next(), t.next()) {
 			(Some(a), Some(t)) => {
 				if a != t {
-					panic!("Mismatch in Turbosql migrations! {:?} != {:?} {:?}", a, t, path)
+					return Err(Error::MigrationMismatch {
+						path: path.to_owned(),
+						applied: a.clone(),
+						expected: t.clone(),
+					});
 				}
 			}
-			(Some(a), None) => {
-				panic!(
-					"Mismatch in Turbosql migrations! More migrations are applied than target. {:?} {:?}",
-					a, path
-				)
-			}
+			(Some(_), None) => unreachable!("checked above"),
 			(None, Some(t)) => {
-				if !t.starts_with("--") {
-					conn.execute(t, params![]).unwrap();
+				let mut stmt = tx.prepare(t)?;
+				// a read-only migration checks the ones before it, and returns why they failed, or NULL
+				if stmt.readonly() {
+					if let Some(reason) = stmt.query_row(params![], |row| row.get(0))? {
+						return Err(Error::MigrationRejected { path: path.to_owned(), reason });
+					}
+				} else {
+					stmt.execute(params![])?;
 				}
-				conn.execute("INSERT INTO _turbosql_migrations(migration) VALUES(?)", params![t]).unwrap();
+				drop(stmt);
+				tx.execute("INSERT INTO _turbosql_migrations(migration) VALUES(?)", params![t])?;
+				migrated = true;
 			}
 			(None, None) => break,
 		}
 	}
 
-	// TODO: verify schema against output_generated_schema_for_your_information_do_not_edit
+	// foreign keys aren't enforced while migrating, so check that a rebuild left none dangling
+	if migrated {
+		let violations = tx
+			.prepare("SELECT \"table\", rowid, parent FROM pragma_foreign_key_check")?
+			.query_map(params![], |row| {
+				Ok(format!(
+					"{} row {} references a missing {} row",
+					row.get::<_, String>(0)?,
+					row.get::<_, Option<i64>>(1)?.map_or("?".to_string(), |rowid| rowid.to_string()),
+					row.get::<_, String>(2)?
+				))
+			})?
+			.collect::<Result<Vec<_>, _>>()?;
+		if !violations.is_empty() {
+			return Err(Error::MigrationRejected { path: path.to_owned(), reason: violations.join(", ") });
+		}
+	}
+
+	tx.commit()?;
 
-	//    if sql != create_sql {
-	//     println!("{}", sql);
-	//     println!("{}", create_sql);
-	//     panic!("Turbosql sqlite schema does not match! Delete database file to continue.");
-	//    }
+	Ok(())
+}
+
+/// An index as name, whether it is unique, and its columns (`None` for an expression).
+type IndexInfo = (String, bool, Vec<Option<String>>);
 
-	conn.execute("COMMIT", params![]).unwrap();
+/// Indexes created by `CREATE INDEX` on `table`.
+fn table_indexes(conn: &Connection, table: &str) -> Result<Vec<IndexInfo>, Error> {
+	let indexes = conn
+		.prepare("SELECT name, \"unique\" FROM pragma_index_list(?) WHERE origin = 'c' ORDER BY name")?
+		.query_map(params![table], |row| Ok((row.get::<_, String>(0)?, row.get::<_, bool>(1)?)))?
+		.collect::<Result<Vec<_>, _>>()?;
+
+	indexes
+		.into_iter()
+		.map(|(name, unique)| {
+			let columns = conn
+				.prepare("SELECT name FROM pragma_index_info(?) ORDER BY seqno")?
+				.query_map(params![name], |row| row.get(0))?
+				.collect::<Result<Vec<_>, _>>()?;
+			Ok((name, unique, columns))
+		})
+		.collect()
+}
+
+/// A foreign key as column, referenced table and column, and `ON DELETE` action.
+type ForeignKeyInfo = (String, String, String, String);
+
+/// Foreign keys of `table`.
+fn table_foreign_keys(conn: &Connection, table: &str) -> Result<Vec<ForeignKeyInfo>, Error> {
+	Ok(
+		conn
+			.prepare("SELECT \"from\", \"table\", \"to\", on_delete FROM pragma_foreign_key_list(?) ORDER BY \"from\"")?
+			.query_map(params![table], |row| {
+				Ok((row.get(0)?, row.get(1)?, row.get::<_, Option<String>>(2)?.unwrap_or_default(), row.get(3)?))
+			})?
+			.collect::<Result<Vec<_>, _>>()?,
+	)
+}
+
+/// The indexes and foreign keys of each table, as this binary's migrations create them.
+type ExpectedSchema = HashMap<String, (Vec<IndexInfo>, Vec<ForeignKeyInfo>)>;
+
+/// [`ExpectedSchema`] of each database, so its migrations are replayed once per process rather than on every connection.
+static EXPECTED_SCHEMAS: Lazy<Mutex<HashMap<Option<String>, ExpectedSchema>>> = Lazy::new(Default::default);
+
+/// The [`ExpectedSchema`] of `tables` in database `db`, from replaying its migrations into an in-memory database the first time.
+fn expected_schema<'a>(
+	path: &Path,
+	db: Option<&str>,
+	tables: impl Iterator<Item = &'a String>,
+) -> Result<ExpectedSchema, Error> {
+	let mut schemas = EXPECTED_SCHEMAS.lock().unwrap();
+
+	if let Some(schema) = schemas.get(&db.map(ToOwned::to_owned)) {
+		return Ok(schema.clone());
+	}
+
+	let mut conn = Connection::open_in_memory()?;
+	apply_migrations(&mut conn, path, db)?;
+
+	let schema = tables
+		.map(|name| Ok((name.clone(), (table_indexes(&conn, name)?, table_foreign_keys(&conn, name)?))))
+		.collect::<Result<ExpectedSchema, Error>>()?;
+
+	schemas.insert(db.map(ToOwned::to_owned), schema.clone());
+
+	Ok(schema)
+}
+
+/// Compare the columns, indexes and foreign keys of the tables generated by `#[derive(Turbosql)]` against `migrations.toml`, returning a description of each difference.
+///
+/// Other tables, and triggers and indexes added by hand, aren't compared.
+fn schema_differences(conn: &Connection, path: &Path, db: Option<&str>) -> Result<Vec<String>, Error> {
+	let toml_decoded = embedded_migrations_toml(db);
+
+	let Some(tables) = toml_decoded.output_generated_tables_do_not_edit else {
+		return Ok(Vec::new());
+	};
+
+	// the schema this binary's migrations produce, for the indexes and foreign keys declared in them
+	let expected = expected_schema(path, db, tables.keys())?;
+
+	let mut differences = Vec::new();
+
+	for (name, table) in tables {
+		let actual_columns = conn
+			.prepare("SELECT name, type, \"notnull\", pk FROM pragma_table_info(?)")?
+			.query_map(params![name], |row| {
+				Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, bool>(2)?, row.get::<_, bool>(3)?))
+			})?
+			.collect::<Result<Vec<_>, _>>()?;
+
+		if actual_columns.is_empty() {
+			differences.push(format!("table {} is missing", name));
+			continue;
+		}
+
+		for column in &table.columns {
+			let Some((_, ty, notnull, pk)) = actual_columns.iter().find(|(n, ..)| n == &column.name) else {
+				differences.push(format!("table {}: column {} is missing", name, column.name));
+				continue;
+			};
+			let actual_sql_type = match (notnull, pk) {
+				(_, true) => format!("{} PRIMARY KEY", ty),
+				(true, false) => format!("{} NOT NULL", ty),
+				(false, false) => ty.clone(),
+			};
+			if actual_sql_type != column.sql_type {
+				differences.push(format!(
+					"table {}: column {} is {}, expected {}",
+					name, column.name, actual_sql_type, column.sql_type
+				));
+			}
+		}
+
+		for (column, ..) in &actual_columns {
+			if !table.columns.iter().any(|c| &c.name == column) {
+				differences.push(format!("table {}: unexpected column {}", name, column));
+			}
+		}
+
+		let (expected_indexes, expected_foreign_keys) = &expected[&name];
+
+		let actual_indexes = table_indexes(conn, &name)?;
+		for (index, unique, columns) in expected_indexes {
+			match actual_indexes.iter().find(|(n, ..)| n == index) {
+				None => differences.push(format!("table {}: index {} is missing", name, index)),
+				Some((_, actual_unique, actual_columns)) if (actual_unique, actual_columns) != (unique, columns) => {
+					differences.push(format!("table {}: index {} differs", name, index))
+				}
+				Some(_) => (),
+			}
+		}
+
+		let actual_foreign_keys = table_foreign_keys(conn, &name)?;
+		for fk @ (column, target, target_column, on_delete) in expected_foreign_keys {
+			if !actual_foreign_keys.contains(fk) {
+				differences.push(format!(
+					"table {}: foreign key {} REFERENCES {}({}) ON DELETE {} is missing",
+					name, column, target, target_column, on_delete
+				));
+			}
+		}
+		for fk @ (column, target, target_column, on_delete) in &actual_foreign_keys {
+			if !expected_foreign_keys.contains(fk) {
+				differences.push(format!(
+					"table {}: unexpected foreign key {} REFERENCES {}({}) ON DELETE {}",
+					name, column, target, target_column, on_delete
+				));
+			}
+		}
+	}
+
+	Ok(differences)
+}
+
+/// Check the schema according to the config's [`SchemaCheck`].
+fn verify_schema(conn: &Connection, path: &Path, db: Option<&str>, check: SchemaCheck) -> Result<(), Error> {
+	if check == SchemaCheck::Off {
+		return Ok(());
+	}
+
+	let differences = schema_differences(conn, path, db)?;
+
+	if differences.is_empty() {
+		return Ok(());
+	}
+
+	let error = Error::SchemaMismatch { path: path.to_owned(), differences };
+
+	match check {
+		SchemaCheck::Error => Err(error),
+		_ => {
+			log::warn!("{}", error);
+			Ok(())
+		}
+	}
 }
 
 /// Result of a [`checkpoint`].
@@ -197,10 +900,7 @@ pub fn checkpoint() -> Result<CheckpointResult, Error> {
 	__TURBOSQL_DB.with(|_| {});
 	let db_path = __DB_PATH.lock().unwrap();
 
-	let conn = Connection::open_with_flags(
-		db_path.path.as_ref().unwrap(),
-		OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_NO_MUTEX,
-	)?;
+	let conn = db_path.config.connect()?;
 
 	let result = conn.query_row("PRAGMA wal_checkpoint(PASSIVE)", params![], |row| {
 		Ok(CheckpointResult { busy: row.get(0)?, log: row.get(1)?, checkpointed: row.get(2)? })
@@ -211,72 +911,197 @@ pub fn checkpoint() -> Result<CheckpointResult, Error> {
 	Ok(result)
 }
 
-fn open_db() -> Connection {
-	let mut db_path = __DB_PATH.lock().unwrap();
+fn open_db(db: Option<&str>) -> Result<Connection, Error> {
+	with_db_path(db, |db_path| {
+		if db_path.config.path.is_none() {
+			#[cfg(not(feature = "test"))]
+			let path = {
+				let exe_stem = std::env::current_exe()
+					.ok()
+					.and_then(|exe| exe.file_stem().map(ToOwned::to_owned))
+					.ok_or(Error::OtherError("Unable to determine executable name for database path"))?;
+				let exe_stem_lossy = exe_stem.to_string_lossy();
+
+				let path = directories_next::ProjectDirs::from("org", &exe_stem_lossy, &exe_stem_lossy)
+					.ok_or(Error::OtherError("Unable to determine data directory for database path"))?
+					.data_dir()
+					.to_owned();
 
-	if db_path.path.is_none() {
-		#[cfg(not(feature = "test"))]
-		let path = {
-			let exe_stem = std::env::current_exe().unwrap().file_stem().unwrap().to_owned();
-			let exe_stem_lossy = exe_stem.to_string_lossy();
+				std::fs::create_dir_all(&path)
+					.map_err(|_| Error::OtherError("Unable to create data directory for database path"))?;
 
-			let path = directories_next::ProjectDirs::from("org", &exe_stem_lossy, &exe_stem_lossy)
-				.unwrap()
-				.data_dir()
-				.to_owned();
+				match db {
+					None => path.join(exe_stem).with_extension("sqlite"),
+					Some(name) => path.join(format!("{}-{}.sqlite", exe_stem_lossy, name)),
+				}
+			};
 
-			std::fs::create_dir_all(&path).unwrap();
+			#[cfg(feature = "test")]
+			let path = Path::new(":memory:").to_owned();
 
-			path.join(exe_stem).with_extension("sqlite")
-		};
+			db_path.config.path = Some(path);
+		}
 
-		#[cfg(feature = "test")]
-		let path = Path::new(":memory:").to_owned();
+		let config = &db_path.config;
+		let path = config.path.as_ref().unwrap();
 
-		db_path.path = Some(path);
-	}
+		log::debug!("opening db at {:?}", path);
 
-	log::debug!("opening db at {:?}", db_path.path.as_ref().unwrap());
+		let mut conn = config.connect()?;
 
-	// We are handling the mutex by being thread_local, so SQLite can be opened in no-mutex mode; see:
-	// https://www.mail-archive.com/sqlite-users@mailinglists.sqlite.org/msg112907.html
+		// Each connection to `:memory:` is a separate, empty database, so it always needs migrating.
+		if !db_path.opened || path == Path::new(":memory:") {
+			if !config.read_only {
+				run_migrations(&mut conn, path, db)?;
+			}
+			verify_schema(&conn, path, db, config.schema_check)?;
+		}
 
-	let mut conn = Connection::open_with_flags(
-		db_path.path.as_ref().unwrap(),
-		OpenFlags::SQLITE_OPEN_READ_WRITE
-			| OpenFlags::SQLITE_OPEN_CREATE
-			| OpenFlags::SQLITE_OPEN_NO_MUTEX,
-	)
-	.expect("rusqlite::Connection::open_with_flags");
-
-	conn
-		.execute_batch(
-			r#"
-				PRAGMA busy_timeout=3000;
-				PRAGMA auto_vacuum=INCREMENTAL;
-				PRAGMA journal_mode=WAL;
-				PRAGMA wal_autocheckpoint=8000;
-				PRAGMA synchronous=NORMAL;
-			"#,
-		)
-		.expect("Execute PRAGMAs");
-
-	if !db_path.opened {
-		run_migrations(&mut conn, db_path.path.as_ref().unwrap());
 		db_path.opened = true;
+
+		Ok(conn)
+	})
+}
+
+/// Open this thread's connection to the main database now, running any pending migrations, and report failures as an [`Error`].
+///
+/// Otherwise, the database is opened lazily by the first Turbosql call on each thread, which panics if that fails. Calling `open()` at startup lets an app show a real message, for example when an old binary is run against a database that a newer version has migrated:
+///
+/// ```rust,no_run
+/// match turbosql::open() {
+///     Ok(()) => (),
+///     Err(turbosql::Error::DatabaseNewerThanBinary { path, .. }) => {
+///         eprintln!("{:?} was created by a newer version of this app; please upgrade.", path);
+///         std::process::exit(1);
+///     }
+///     Err(e) => panic!("{}", e),
+/// }
+/// ```
+///
+/// Calling `open()` when this thread's connection is already open does nothing.
+pub fn open() -> Result<(), Error> {
+	if !MAIN_DB_INITIALIZED.with(Cell::get) {
+		let conn = open_db(None)?;
+		PENDING_MAIN_DB.with(|pending| *pending.borrow_mut() = Some(conn));
+		__TURBOSQL_DB.with(|_| {});
 	}
+	Ok(())
+}
 
-	conn
+thread_local! {
+	/// Connection opened by [`open`], waiting to be moved into `__TURBOSQL_DB`.
+	static PENDING_MAIN_DB: RefCell<Option<Connection>> = const { RefCell::new(None) };
+	static MAIN_DB_INITIALIZED: Cell<bool> = const { Cell::new(false) };
+}
+
+fn init_main_db() -> Connection {
+	MAIN_DB_INITIALIZED.with(|initialized| initialized.set(true));
+	PENDING_MAIN_DB
+		.with(|pending| pending.borrow_mut().take())
+		.unwrap_or_else(|| open_db(None).unwrap_or_else(|e| panic!("{}", e)))
 }
 
 thread_local! {
 	#[doc(hidden)]
-	pub static __TURBOSQL_DB: RefCell<Connection> = RefCell::new(open_db());
+	pub static __TURBOSQL_DB: RefCell<Connection> = RefCell::new(init_main_db());
+	static NAMED_DBS: RefCell<HashMap<&'static str, Rc<RefCell<Connection>>>> = Default::default();
+}
+
+/// Returns a handle to the named database `name`, which holds the tables of structs declared with `#[turbosql(db = "name")]`.
+///
+/// Each named database is a separate SQLite file with its own migrations, and each thread has its own connection to it. Use `db = "name"` as the first argument to `select!`, `execute!` and `update!` to query it.
+pub fn db(name: &'static str) -> Db {
+	Db { name }
+}
+
+/// Handle to a named database. See [`db`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Db {
+	name: &'static str,
+}
+
+impl Db {
+	/// Like [`open`], but for this database.
+	pub fn open(self) -> Result<(), Error> {
+		if !NAMED_DBS.with(|dbs| dbs.borrow().contains_key(self.name)) {
+			let conn = Rc::new(RefCell::new(open_db(Some(self.name))?));
+			NAMED_DBS.with(|dbs| dbs.borrow_mut().insert(self.name, conn));
+		}
+		Ok(())
+	}
+
+	/// Returns the path to this database.
+	pub fn path(&self) -> PathBuf {
+		self.with(|_| {});
+		NAMED_DB_PATHS.lock().unwrap()[self.name].config.path.clone().unwrap()
+	}
+
+	/// Like [`transaction`], but on this database.
+	pub fn transaction<T, F>(self, f: F) -> Result<T, Error>
+	where
+		F: FnOnce() -> Result<T, Error>,
+	{
+		run_transaction(Some(self), "BEGIN DEFERRED TRANSACTION", f)
+	}
+
+	/// Like [`transaction_immediate`], but on this database.
+	pub fn transaction_immediate<T, F>(self, f: F) -> Result<T, Error>
+	where
+		F: FnOnce() -> Result<T, Error>,
+	{
+		run_transaction(Some(self), "BEGIN IMMEDIATE TRANSACTION", f)
+	}
+
+	/// Like [`transaction_exclusive`], but on this database.
+	pub fn transaction_exclusive<T, F>(self, f: F) -> Result<T, Error>
+	where
+		F: FnOnce() -> Result<T, Error>,
+	{
+		run_transaction(Some(self), "BEGIN EXCLUSIVE TRANSACTION", f)
+	}
+
+	/// Run `f` with this thread's connection to this database, opening it first if needed.
+	#[doc(hidden)]
+	pub fn with<R>(&self, f: impl FnOnce(&RefCell<Connection>) -> R) -> R {
+		// Don't hold the map borrow while `f` runs, so `f` can use other named databases.
+		let conn = NAMED_DBS.with(|dbs| {
+			dbs
+				.borrow_mut()
+				.entry(self.name)
+				.or_insert_with(|| {
+					Rc::new(RefCell::new(open_db(Some(self.name)).unwrap_or_else(|e| panic!("{}", e))))
+				})
+				.clone()
+		});
+		f(&conn)
+	}
+}
+
+fn with_connection<R>(db: Option<Db>, f: impl FnOnce(&RefCell<Connection>) -> R) -> R {
+	match db {
+		None => __TURBOSQL_DB.with(f),
+		Some(db) => db.with(f),
+	}
+}
+
+/// Like [`with_connection`], but does nothing if the thread-local connection is already gone, e.g. during thread teardown.
+fn try_with_connection(db: Option<Db>, f: impl FnOnce(&RefCell<Connection>)) {
+	match db {
+		None => {
+			let _ = __TURBOSQL_DB.try_with(f);
+		}
+		Some(db) => {
+			if let Some(conn) = NAMED_DBS.try_with(|dbs| dbs.borrow().get(db.name).cloned()).ok().flatten() {
+				f(&conn);
+			}
+		}
+	}
 }
 
 /// Set the local path and filename where Turbosql will store the underlying SQLite database.
 ///
 /// Must be called before any usage of Turbosql macros or will return an error.
+#[deprecated(note = "use `turbosql::Config::new().path(path).apply()`")]
 pub fn set_db_path(path: &Path) -> Result<(), Error> {
 	let mut db_path = __DB_PATH.lock().unwrap();
 
@@ -284,7 +1109,123 @@ pub fn set_db_path(path: &Path) -> Result<(), Error> {
 		return Err(Error::OtherError("Trying to set path when DB is already opened"));
 	}
 
-	db_path.path = Some(path.to_owned());
+	db_path.config.path = Some(path.to_owned());
 
 	Ok(())
 }
+
+/// Run `f` inside a `BEGIN DEFERRED` transaction on this thread's database connection.
+///
+/// The transaction is committed if `f` returns `Ok`, and rolled back if `f` returns `Err` or panics. All `select!`, `execute!`, `update!` and `Turbosql` method calls made from within `f` on the same thread run inside the transaction.
+///
+/// Transactions may be nested. An inner call becomes a `SAVEPOINT`, which is released on `Ok` and rolled back on `Err` or panic, undoing only the inner call's work; the outer transaction continues and decides whether everything is finally committed.
+///
+/// ```rust
+/// # use turbosql::{Turbosql, execute};
+/// # #[derive(Turbosql, Default)]
+/// # struct Person { rowid: Option<i64>, name: Option<String>, age: Option<i64>, image_jpg: Option<Vec<u8>> }
+/// let rowid = turbosql::transaction(|| {
+///     let rowid = Person { age: Some(21), ..Default::default() }.insert()?;
+///     execute!("UPDATE person SET age = age + 1 WHERE rowid = ?", rowid)?;
+///     Ok(rowid)
+/// })?;
+/// # Ok::<(), turbosql::Error>(())
+/// ```
+pub fn transaction<T, F>(f: F) -> Result<T, Error>
+where
+	F: FnOnce() -> Result<T, Error>,
+{
+	run_transaction(None, "BEGIN DEFERRED TRANSACTION", f)
+}
+
+/// Like [`transaction`], but starts with `BEGIN IMMEDIATE`, acquiring the write lock up front. When nested, this is an ordinary savepoint.
+pub fn transaction_immediate<T, F>(f: F) -> Result<T, Error>
+where
+	F: FnOnce() -> Result<T, Error>,
+{
+	run_transaction(None, "BEGIN IMMEDIATE TRANSACTION", f)
+}
+
+/// Like [`transaction`], but starts with `BEGIN EXCLUSIVE`. When nested, this is an ordinary savepoint.
+pub fn transaction_exclusive<T, F>(f: F) -> Result<T, Error>
+where
+	F: FnOnce() -> Result<T, Error>,
+{
+	run_transaction(None, "BEGIN EXCLUSIVE TRANSACTION", f)
+}
+
+thread_local! {
+	/// Open transaction depth of each of this thread's connections, keyed by database name.
+	static TRANSACTION_DEPTH: RefCell<HashMap<Option<&'static str>, usize>> = Default::default();
+}
+
+/// Tracks one level of an open transaction or savepoint. If dropped without being committed (because the closure returned `Err` or panicked), rolls back that level.
+struct TransactionGuard {
+	db: Option<Db>,
+	savepoint: Option<String>,
+	finished: bool,
+}
+
+impl TransactionGuard {
+	fn commit(&mut self) -> Result<(), Error> {
+		let sql = match &self.savepoint {
+			Some(name) => format!("RELEASE {}", name),
+			None => "COMMIT".to_string(),
+		};
+		with_connection(self.db, |db| db.borrow().execute_batch(&sql))?;
+		self.finished = true;
+		Ok(())
+	}
+}
+
+impl Drop for TransactionGuard {
+	fn drop(&mut self) {
+		if !self.finished {
+			let sql = match &self.savepoint {
+				// ROLLBACK TO leaves the savepoint on the stack, so release it as well.
+				Some(name) => format!("ROLLBACK TO {0}; RELEASE {0}", name),
+				None => "ROLLBACK".to_string(),
+			};
+			try_with_connection(self.db, |db| {
+				if let Ok(db) = db.try_borrow() {
+					let _ = db.execute_batch(&sql);
+				}
+			});
+		}
+		let key = self.db.map(|db| db.name);
+		let _ = TRANSACTION_DEPTH.try_with(|depth| {
+			if let Some(depth) = depth.borrow_mut().get_mut(&key) {
+				*depth = depth.saturating_sub(1);
+			}
+		});
+	}
+}
+
+fn run_transaction<T, F>(db: Option<Db>, begin: &str, f: F) -> Result<T, Error>
+where
+	F: FnOnce() -> Result<T, Error>,
+{
+	let key = db.map(|db| db.name);
+	let depth = TRANSACTION_DEPTH.with(|d| d.borrow().get(&key).copied().unwrap_or_default());
+
+	// Nested scopes become savepoints, so a failing inner scope only undoes its own work.
+	let savepoint = (depth > 0).then(|| format!("turbosql_savepoint_{}", depth));
+
+	// Don't hold the RefCell borrow while `f` runs; the macros inside it borrow the connection themselves.
+	with_connection(db, |conn| match &savepoint {
+		Some(name) => conn.borrow().execute_batch(&format!("SAVEPOINT {}", name)),
+		None => conn.borrow().execute_batch(begin),
+	})?;
+
+	TRANSACTION_DEPTH.with(|d| d.borrow_mut().insert(key, depth + 1));
+
+	let mut guard = TransactionGuard { db, savepoint, finished: false };
+
+	let result = f();
+
+	if result.is_ok() {
+		guard.commit()?;
+	}
+
+	result
+}
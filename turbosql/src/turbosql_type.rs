@@ -0,0 +1,310 @@
+//! The [`TurbosqlType`] trait, through which generated code converts fields to and from their columns.
+
+use super::{Error, Ref};
+use rusqlite::types::{FromSql, FromSqlResult, ToSql, ToSqlOutput, Value, ValueRef};
+use serde::{de::DeserializeOwned, Serialize};
+use std::marker::PhantomData;
+
+/// A field type with its own column type and conversions, implemented for the primitive types Turbosql stores natively.
+///
+/// Implement it, or `#[derive(TurbosqlType)]` on a single-field struct, to store your own types natively. Since `#[derive(Turbosql)]` generates migrations before types are resolved, it only knows the types above by name, and fields of other types declare their column with `#[turbosql(sql_type = "...")]`. Each field's column is checked against [`SQL_TYPE`](TurbosqlType::SQL_TYPE) at compile time, and a field with a `TurbosqlType` but no `sql_type` fails to compile instead of being stored as JSON:
+///
+/// ```
+/// # use turbosql::{Turbosql, TurbosqlType};
+/// #[derive(TurbosqlType, Default, Clone)]
+/// struct Email(String);
+///
+/// #[derive(Turbosql, Default)]
+/// struct Contact {
+///     rowid: Option<i64>,
+///     #[turbosql(sql_type = "TEXT")]
+///     email: Option<Email>,
+/// }
+/// ```
+pub trait TurbosqlType: Sized {
+	/// Column type: `"INTEGER"`, `"REAL"`, `"TEXT"`, `"BLOB"` or `"ANY"`.
+	const SQL_TYPE: &'static str;
+	/// Whether the column is `NOT NULL`. `Option<T>` sets this to `false`.
+	const NOT_NULL: bool = true;
+	/// Converts this value to SQL. Named so as not to clash with rusqlite's `ToSql::to_sql`.
+	fn to_sql_output(&self) -> rusqlite::Result<ToSqlOutput<'_>>;
+	/// Converts a column value from SQL.
+	fn from_sql_value(value: ValueRef<'_>) -> FromSqlResult<Self>;
+}
+
+impl<T: TurbosqlType> TurbosqlType for Option<T> {
+	const SQL_TYPE: &'static str = T::SQL_TYPE;
+	const NOT_NULL: bool = false;
+	fn to_sql_output(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+		match self {
+			Some(value) => T::to_sql_output(value),
+			None => Ok(ToSqlOutput::Owned(Value::Null)),
+		}
+	}
+	fn from_sql_value(value: ValueRef<'_>) -> FromSqlResult<Self> {
+		match value {
+			ValueRef::Null => Ok(None),
+			value => T::from_sql_value(value).map(Some),
+		}
+	}
+}
+
+/// `TurbosqlType` for types with rusqlite's `ToSql` and `FromSql`.
+macro_rules! impl_turbosql_type {
+	($($sql_type:literal => $($ty:ty),+;)*) => {$($(
+		impl TurbosqlType for $ty {
+			const SQL_TYPE: &'static str = $sql_type;
+			fn to_sql_output(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+				ToSql::to_sql(self)
+			}
+			fn from_sql_value(value: ValueRef<'_>) -> FromSqlResult<Self> {
+				FromSql::column_result(value)
+			}
+		}
+	)+)*};
+}
+
+impl_turbosql_type! {
+	"INTEGER" => i8, u8, i16, u16, i32, u32, i64, u64, bool;
+	"REAL" => f32, f64;
+	"TEXT" => String, Box<str>, std::rc::Rc<str>, std::sync::Arc<str>;
+	"BLOB" => Vec<u8>;
+}
+
+impl<const N: usize> TurbosqlType for [u8; N] {
+	const SQL_TYPE: &'static str = "BLOB";
+	fn to_sql_output(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+		ToSql::to_sql(self)
+	}
+	fn from_sql_value(value: ValueRef<'_>) -> FromSqlResult<Self> {
+		FromSql::column_result(value)
+	}
+}
+
+impl<T> TurbosqlType for Ref<T> {
+	const SQL_TYPE: &'static str = "INTEGER";
+	fn to_sql_output(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+		ToSql::to_sql(self)
+	}
+	fn from_sql_value(value: ValueRef<'_>) -> FromSqlResult<Self> {
+		FromSql::column_result(value)
+	}
+}
+
+#[cfg(feature = "i128_blob")]
+impl_turbosql_type! {
+	"BLOB" => i128;
+}
+
+#[cfg(feature = "chrono")]
+impl_turbosql_type! {
+	"TEXT" => chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Local>, chrono::DateTime<chrono::FixedOffset>,
+		chrono::NaiveDateTime, chrono::NaiveDate, chrono::NaiveTime;
+}
+
+#[cfg(feature = "time")]
+impl_turbosql_type! {
+	"TEXT" => time::OffsetDateTime, time::PrimitiveDateTime, time::Date, time::Time;
+}
+
+#[cfg(feature = "jiff")]
+impl_turbosql_type! {
+	"TEXT" => jiff::Timestamp, jiff::civil::DateTime, jiff::civil::Date, jiff::civil::Time;
+}
+
+#[cfg(feature = "uuid")]
+impl_turbosql_type! {
+	"BLOB" => uuid::Uuid;
+}
+
+#[cfg(feature = "url")]
+impl_turbosql_type! {
+	"TEXT" => url::Url;
+}
+
+/// Binds a field by reference through its [`TurbosqlType`] impl if it has one, or as JSON otherwise, with `(&AutoBind(&value)).turbosql_bind()`.
+#[doc(hidden)]
+pub struct AutoBind<'a, T>(pub &'a T);
+
+/// Reads a field through its [`TurbosqlType`] impl if it has one, or as JSON otherwise, with `(&AutoRead::of(|row: &Row| &row.field)).turbosql_read(row, i)`.
+#[doc(hidden)]
+pub struct AutoRead<T>(PhantomData<T>);
+
+/// Reads a column through `T`'s [`TurbosqlType`] impl, with rusqlite's errors for `FromSql`.
+struct Typed<T>(T);
+
+impl<T: TurbosqlType> FromSql for Typed<T> {
+	fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+		T::from_sql_value(value).map(Typed)
+	}
+}
+
+impl<T> AutoRead<T> {
+	/// Reads the type of the field that `field` borrows from a row of struct `S`.
+	pub fn of<S>(_field: fn(&S) -> &T) -> Self {
+		AutoRead(PhantomData)
+	}
+}
+
+// Method calls on `&AutoBind` and `&AutoRead` pick these impls for `TurbosqlType`s, and the `&&` impls below otherwise.
+
+#[doc(hidden)]
+pub trait BindTyped<'a> {
+	fn turbosql_bind(&self) -> Result<ToSqlOutput<'a>, Error>;
+}
+
+impl<'a, T: TurbosqlType> BindTyped<'a> for AutoBind<'a, T> {
+	fn turbosql_bind(&self) -> Result<ToSqlOutput<'a>, Error> {
+		Ok(self.0.to_sql_output()?)
+	}
+}
+
+#[doc(hidden)]
+pub trait BindJson<'a> {
+	fn turbosql_bind(&self) -> Result<ToSqlOutput<'a>, Error>;
+}
+
+impl<'a, T: Serialize> BindJson<'a> for &AutoBind<'a, T> {
+	fn turbosql_bind(&self) -> Result<ToSqlOutput<'a>, Error> {
+		Ok(ToSqlOutput::from(serde_json::to_string(self.0)?))
+	}
+}
+
+#[doc(hidden)]
+pub trait ReadTyped<T> {
+	fn turbosql_read(&self, row: &rusqlite::Row, i: usize) -> Result<T, Error>;
+}
+
+impl<T: TurbosqlType> ReadTyped<T> for AutoRead<T> {
+	fn turbosql_read(&self, row: &rusqlite::Row, i: usize) -> Result<T, Error> {
+		Ok(row.get::<_, Typed<T>>(i)?.0)
+	}
+}
+
+#[doc(hidden)]
+pub trait ReadJson<T> {
+	fn turbosql_read(&self, row: &rusqlite::Row, i: usize) -> Result<T, Error>;
+}
+
+impl<T: DeserializeOwned> ReadJson<T> for &AutoRead<T> {
+	fn turbosql_read(&self, row: &rusqlite::Row, i: usize) -> Result<T, Error> {
+		let string: String = row.get(i)?;
+		Ok(serde_json::from_str(&string)?)
+	}
+}
+
+/// A unit-only enum stored as one of a fixed set of values, implemented by `#[derive(TurbosqlEnum)]`.
+///
+/// Variants are stored as their names with `#[turbosql(repr = "text")]`, the default, or as their discriminants with `#[turbosql(repr = "int")]`. Fields of the enum list the same values with `#[turbosql(enum_values(...))]`, which sets the column type and adds a `CHECK (column IN (...))` constraint, and is checked against [`SQL_VALUES`](TurbosqlEnum::SQL_VALUES) at compile time:
+///
+/// ```
+/// # use turbosql::{Turbosql, TurbosqlEnum};
+/// #[derive(TurbosqlEnum, Default, Clone, Copy)]
+/// enum Status {
+///     #[default]
+///     Active,
+///     Archived,
+/// }
+///
+/// #[derive(Turbosql, Default)]
+/// struct Project {
+///     rowid: Option<i64>,
+///     #[turbosql(enum_values("Active", "Archived"))]
+///     status: Status,
+/// }
+/// ```
+pub trait TurbosqlEnum: TurbosqlType {
+	/// SQL literals of the variants in declaration order, e.g. `'Active'` or `0`.
+	const SQL_VALUES: &'static [&'static str];
+}
+
+impl<T: TurbosqlEnum> TurbosqlEnum for Option<T> {
+	const SQL_VALUES: &'static [&'static str] = T::SQL_VALUES;
+}
+
+const fn str_eq(a: &str, b: &str) -> bool {
+	let (a, b) = (a.as_bytes(), b.as_bytes());
+	let mut same = a.len() == b.len();
+	let mut i = 0;
+	while same && i < a.len() {
+		same = a[i] == b[i];
+		i += 1;
+	}
+	same
+}
+
+/// `TypeProbe::<T>::SQL_TYPE` is `Some(T::SQL_TYPE)` if `T` is a [`TurbosqlType`], and `None` from [`NoTurbosqlType`] otherwise.
+#[doc(hidden)]
+pub struct TypeProbe<T>(PhantomData<T>);
+
+impl<T: TurbosqlType> TypeProbe<T> {
+	pub const SQL_TYPE: Option<&'static str> = Some(T::SQL_TYPE);
+	pub const NOT_NULL: bool = T::NOT_NULL;
+}
+
+#[doc(hidden)]
+pub trait NoTurbosqlType {
+	const SQL_TYPE: Option<&'static str> = None;
+	const NOT_NULL: bool = false;
+}
+
+impl<T> NoTurbosqlType for TypeProbe<T> {}
+
+/// `EnumProbe::<T>::SQL_VALUES` is `Some(T::SQL_VALUES)` if `T` is a [`TurbosqlEnum`], and `None` from [`NotTurbosqlEnum`] otherwise.
+#[doc(hidden)]
+pub struct EnumProbe<T>(PhantomData<T>);
+
+impl<T: TurbosqlEnum> EnumProbe<T> {
+	pub const SQL_VALUES: Option<&'static [&'static str]> = Some(T::SQL_VALUES);
+}
+
+#[doc(hidden)]
+pub trait NotTurbosqlEnum {
+	const SQL_VALUES: Option<&'static [&'static str]> = None;
+}
+
+impl<T> NotTurbosqlEnum for EnumProbe<T> {}
+
+/// Fails compilation if a field's column, from `#[turbosql(sql_type = "...")]`, `#[turbosql(enum_values(...))]` or its type name, doesn't match its [`TurbosqlType`], or its `enum_values` don't list its [`TurbosqlEnum`]'s values in order, probed with [`TypeProbe`] and [`EnumProbe`]. A `sql_type` of `None` is a field whose type name isn't known, stored as JSON, which must not have a `TurbosqlType`.
+#[doc(hidden)]
+pub const fn check_sql_type(
+	sql_type: Option<&str>,
+	not_null: bool,
+	declared: bool,
+	field_sql_type: Option<&str>,
+	field_not_null: bool,
+	sql_values: &[&str],
+	field_sql_values: Option<&[&str]>,
+) {
+	match (sql_type, field_sql_type, field_sql_values) {
+		(None, Some(_), Some(_)) => panic!(
+			"the field's type derives TurbosqlEnum, but #[derive(Turbosql)] can't tell its column from the type's name; list its variants with #[turbosql(enum_values(...))]"
+		),
+		(None, Some(_), None) => panic!(
+			"the field's type implements TurbosqlType, but #[derive(Turbosql)] can't tell its column from the type's name; declare the column with #[turbosql(sql_type = ...)] set to SQL_TYPE"
+		),
+		(Some(sql_type), Some(field_sql_type), _) if !str_eq(sql_type, field_sql_type) => panic!(
+			"the field's column type differs from its TurbosqlType::SQL_TYPE; declare the column with #[turbosql(sql_type = ...)] set to SQL_TYPE"
+		),
+		(_, Some(_), _) if not_null != field_not_null => {
+			panic!("Option<T> fields must have a TurbosqlType with NOT_NULL = false, and other fields NOT_NULL = true")
+		}
+		(_, None, _) if declared => panic!("#[turbosql(sql_type = ...)] requires the field type to implement TurbosqlType"),
+		_ => (),
+	}
+	if sql_values.is_empty() {
+		return;
+	}
+	let Some(field_sql_values) = field_sql_values else {
+		panic!("#[turbosql(enum_values(...))] requires the field type to derive TurbosqlEnum")
+	};
+	let mut same = sql_values.len() == field_sql_values.len();
+	let mut i = 0;
+	while same && i < sql_values.len() {
+		same = str_eq(sql_values[i], field_sql_values[i]);
+		i += 1;
+	}
+	if !same {
+		panic!("#[turbosql(enum_values(...))] must list the enum's variant names (repr = \"text\") or discriminants (repr = \"int\") in order")
+	}
+}
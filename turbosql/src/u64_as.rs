@@ -0,0 +1,61 @@
+//! `u64`s stored as big-endian `BLOB`s or decimal `TEXT`, for `#[turbosql(u64_as = "...")]` fields.
+
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, Value, ValueRef};
+
+/// Binds or reads a `u64` as 8 big-endian bytes, the way `#[turbosql(u64_as = "blob_be")]` fields are stored.
+///
+/// SQLite compares `BLOB`s bytewise, so these sort numerically in `ORDER BY` and range queries, e.g. `select!(Vec<Hash> "WHERE value >= ?", U64BlobBe(start))?`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct U64BlobBe<T>(pub T);
+
+/// Binds or reads a `u64` as decimal `TEXT`, the way `#[turbosql(u64_as = "text")]` fields are stored. These sort as text, not numerically.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct U64Text<T>(pub T);
+
+fn blob_be(value: u64) -> Value {
+	Value::Blob(value.to_be_bytes().to_vec())
+}
+
+fn text(value: u64) -> Value {
+	Value::Text(value.to_string())
+}
+
+impl FromSql for U64BlobBe<u64> {
+	fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+		<[u8; 8]>::column_result(value).map(|bytes| U64BlobBe(u64::from_be_bytes(bytes)))
+	}
+}
+
+impl FromSql for U64Text<u64> {
+	fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+		value.as_str()?.parse().map(U64Text).map_err(FromSqlError::other)
+	}
+}
+
+/// `ToSql` for `u64` and `Option<u64>` via `$to_value`, and `FromSql` for `Option<u64>` as `NULL` or the same as a plain `u64`.
+macro_rules! impl_u64_as {
+	($($wrapper:ident => $to_value:ident),*) => {$(
+		impl ToSql for $wrapper<u64> {
+			fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+				Ok(ToSqlOutput::Owned($to_value(self.0)))
+			}
+		}
+
+		impl ToSql for $wrapper<Option<u64>> {
+			fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+				Ok(ToSqlOutput::Owned(self.0.map_or(Value::Null, $to_value)))
+			}
+		}
+
+		impl FromSql for $wrapper<Option<u64>> {
+			fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+				match value {
+					ValueRef::Null => Ok($wrapper(None)),
+					value => $wrapper::<u64>::column_result(value).map(|$wrapper(value)| $wrapper(Some(value))),
+				}
+			}
+		}
+	)*};
+}
+
+impl_u64_as!(U64BlobBe => blob_be, U64Text => text);
@@ -0,0 +1,81 @@
+//! Timestamps stored as integer milliseconds since the Unix epoch, for `#[turbosql(datetime_as = "unix_ms")]` fields.
+
+use rusqlite::types::{FromSql, FromSqlResult, ToSql, ToSqlOutput, Value, ValueRef};
+#[cfg(any(feature = "chrono", feature = "time", feature = "jiff"))]
+use rusqlite::types::FromSqlError;
+
+/// Binds or reads a timestamp as integer milliseconds since the Unix epoch, the way `#[turbosql(datetime_as = "unix_ms")]` fields are stored.
+///
+/// Wrap parameters compared against such columns, e.g. `select!(Vec<Event> "WHERE at > " UnixMs(since))?`. Fields stored as the default ISO-8601 `TEXT` bind their type directly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UnixMs<T>(pub T);
+
+/// A timestamp type that [`UnixMs`] can convert, with or without `Option`. All of these are `Copy`, so generated code wraps field values directly.
+pub trait UnixMsTimestamp: Sized {
+	/// Milliseconds since the Unix epoch, or `None` to store `NULL`.
+	fn to_unix_ms(&self) -> rusqlite::Result<Option<i64>>;
+	/// Converts milliseconds since the Unix epoch, or `None` for `NULL`.
+	fn from_unix_ms(ms: Option<i64>) -> FromSqlResult<Self>;
+}
+
+impl<T: UnixMsTimestamp> UnixMsTimestamp for Option<T> {
+	fn to_unix_ms(&self) -> rusqlite::Result<Option<i64>> {
+		match self {
+			Some(value) => value.to_unix_ms(),
+			None => Ok(None),
+		}
+	}
+	fn from_unix_ms(ms: Option<i64>) -> FromSqlResult<Self> {
+		ms.map(|ms| T::from_unix_ms(Some(ms))).transpose()
+	}
+}
+
+impl<T: UnixMsTimestamp> ToSql for UnixMs<T> {
+	fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+		Ok(match self.0.to_unix_ms()? {
+			Some(ms) => ToSqlOutput::from(ms),
+			None => ToSqlOutput::Owned(Value::Null),
+		})
+	}
+}
+
+impl<T: UnixMsTimestamp> FromSql for UnixMs<T> {
+	fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+		T::from_unix_ms(value.as_i64_or_null()?).map(UnixMs)
+	}
+}
+
+#[cfg(feature = "chrono")]
+impl UnixMsTimestamp for chrono::DateTime<chrono::Utc> {
+	fn to_unix_ms(&self) -> rusqlite::Result<Option<i64>> {
+		Ok(Some(self.timestamp_millis()))
+	}
+	fn from_unix_ms(ms: Option<i64>) -> FromSqlResult<Self> {
+		let ms = ms.ok_or(FromSqlError::InvalidType)?;
+		chrono::DateTime::from_timestamp_millis(ms).ok_or(FromSqlError::OutOfRange(ms))
+	}
+}
+
+#[cfg(feature = "time")]
+impl UnixMsTimestamp for time::OffsetDateTime {
+	fn to_unix_ms(&self) -> rusqlite::Result<Option<i64>> {
+		let ms = self.unix_timestamp_nanos().div_euclid(1_000_000);
+		i64::try_from(ms).map(Some).map_err(|e| rusqlite::Error::ToSqlConversionFailure(e.into()))
+	}
+	fn from_unix_ms(ms: Option<i64>) -> FromSqlResult<Self> {
+		let ms = ms.ok_or(FromSqlError::InvalidType)?;
+		time::OffsetDateTime::from_unix_timestamp_nanos(i128::from(ms) * 1_000_000)
+			.map_err(|_| FromSqlError::OutOfRange(ms))
+	}
+}
+
+#[cfg(feature = "jiff")]
+impl UnixMsTimestamp for jiff::Timestamp {
+	fn to_unix_ms(&self) -> rusqlite::Result<Option<i64>> {
+		Ok(Some(self.as_millisecond()))
+	}
+	fn from_unix_ms(ms: Option<i64>) -> FromSqlResult<Self> {
+		let ms = ms.ok_or(FromSqlError::InvalidType)?;
+		jiff::Timestamp::from_millisecond(ms).map_err(|_| FromSqlError::OutOfRange(ms))
+	}
+}
@@ -0,0 +1,40 @@
+//! `Uuid`s stored as hyphenated `TEXT`, for `#[turbosql(uuid_as = "text")]` fields.
+
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, Value, ValueRef};
+use uuid::Uuid;
+
+/// Binds or reads a `Uuid` as hyphenated `TEXT`, the way `#[turbosql(uuid_as = "text")]` fields are stored.
+///
+/// Wrap parameters compared against such columns, e.g. `select!(Vec<Device> "WHERE id = ?", UuidText(id))?`. Fields stored as 16-byte `BLOB`s with `uuid_as = "blob"` bind `Uuid` directly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UuidText<T>(pub T);
+
+impl ToSql for UuidText<Uuid> {
+	fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+		Ok(ToSqlOutput::from(self.0.hyphenated().to_string()))
+	}
+}
+
+impl ToSql for UuidText<Option<Uuid>> {
+	fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+		match self.0 {
+			Some(uuid) => Ok(ToSqlOutput::from(uuid.hyphenated().to_string())),
+			None => Ok(ToSqlOutput::Owned(Value::Null)),
+		}
+	}
+}
+
+impl FromSql for UuidText<Uuid> {
+	fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+		Uuid::parse_str(value.as_str()?).map(UuidText).map_err(FromSqlError::other)
+	}
+}
+
+impl FromSql for UuidText<Option<Uuid>> {
+	fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+		match value {
+			ValueRef::Null => Ok(UuidText(None)),
+			value => UuidText::<Uuid>::column_result(value).map(|UuidText(uuid)| UuidText(Some(uuid))),
+		}
+	}
+}
@@ -0,0 +1,179 @@
+//! A dedicated database thread for `async` callers, enabled by the `async` feature.
+
+use super::{transaction, Error, Turbosql};
+use once_cell::sync::Lazy;
+use std::future::Future;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{mpsc, Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Jobs run one at a time, in order, on a single thread that owns its own thread-local connection.
+static WORKER: Lazy<mpsc::Sender<Job>> = Lazy::new(|| {
+	let (tx, rx) = mpsc::channel::<Job>();
+	std::thread::Builder::new()
+		.name("turbosql".into())
+		.spawn(move || {
+			for job in rx {
+				job();
+			}
+		})
+		.expect("spawn turbosql database thread");
+	tx
+});
+
+struct Shared<R> {
+	result: Option<std::thread::Result<R>>,
+	waker: Option<Waker>,
+}
+
+/// Future returned by [`run_async`] and the `async` Turbosql APIs. Resolves when the database thread has finished running the closure.
+///
+/// If the closure panics, the panic is resumed in the task that awaits this future.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct DbFuture<R> {
+	shared: Arc<Mutex<Shared<R>>>,
+}
+
+impl<R> Future for DbFuture<R> {
+	type Output = R;
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<R> {
+		let mut shared = self.shared.lock().unwrap();
+		match shared.result.take() {
+			Some(Ok(result)) => Poll::Ready(result),
+			Some(Err(panic)) => resume_unwind(panic),
+			None => {
+				shared.waker = Some(cx.waker().clone());
+				Poll::Pending
+			}
+		}
+	}
+}
+
+/// Run `f` on Turbosql's dedicated database thread, returning a future that resolves to its result.
+///
+/// Any blocking Turbosql calls, including [`transaction`] and friends, may be made inside `f`. Closures run one at a time in the order they were submitted, so everything in one closure happens on a single connection without interleaving.
+///
+/// ```rust
+/// # use turbosql::{Turbosql, select};
+/// # #[derive(Turbosql, Default)]
+/// # struct Person { rowid: Option<i64>, name: Option<String>, age: Option<i64>, image_jpg: Option<Vec<u8>> }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), turbosql::Error> {
+/// let adults = turbosql::run_async(|| select!(Vec<Person> "WHERE age >= 18")).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn run_async<R, F>(f: F) -> DbFuture<R>
+where
+	F: FnOnce() -> R + Send + 'static,
+	R: Send + 'static,
+{
+	let shared = Arc::new(Mutex::new(Shared { result: None, waker: None }));
+	let job_shared = shared.clone();
+
+	let job: Job = Box::new(move || {
+		let result = catch_unwind(AssertUnwindSafe(f));
+		let mut shared = job_shared.lock().unwrap();
+		shared.result = Some(result);
+		if let Some(waker) = shared.waker.take() {
+			waker.wake();
+		}
+	});
+
+	WORKER.send(job).expect("turbosql database thread has stopped");
+
+	DbFuture { shared }
+}
+
+/// Async version of [`transaction`]: runs `f` in a transaction on the database thread.
+///
+/// The whole closure runs on one connection, so the transaction can't be split across threads by the executor.
+pub fn transaction_async<T, F>(f: F) -> DbFuture<Result<T, Error>>
+where
+	F: FnOnce() -> Result<T, Error> + Send + 'static,
+	T: Send + 'static,
+{
+	run_async(move || transaction(f))
+}
+
+/// Async versions of the [`Turbosql`] trait methods, which run on the database thread. Implemented for every `#[derive(Turbosql)]` struct that is `Send`.
+pub trait TurbosqlAsync: Turbosql + Send + Sized + 'static {
+	/// Async version of [`Turbosql::insert`].
+	fn insert_async(self) -> DbFuture<Result<i64, Error>> {
+		run_async(move || self.insert())
+	}
+	/// Async version of [`Turbosql::update`].
+	fn update_async(self) -> DbFuture<Result<usize, Error>> {
+		run_async(move || self.update())
+	}
+	/// Async version of [`Turbosql::upsert`].
+	fn upsert_async(self) -> DbFuture<Result<i64, Error>> {
+		run_async(move || self.upsert())
+	}
+	/// Async version of [`Turbosql::delete`].
+	fn delete_async(self) -> DbFuture<Result<usize, Error>> {
+		run_async(move || self.delete())
+	}
+	/// Async version of [`Turbosql::insert_batch`].
+	fn insert_batch_async(rows: Vec<Self>) -> DbFuture<Result<Vec<i64>, Error>> {
+		run_async(move || Self::insert_batch(&rows.into_iter().map(Box::new).collect::<Vec<_>>()))
+	}
+	/// Async version of [`Turbosql::update_batch`].
+	fn update_batch_async(rows: Vec<Self>) -> DbFuture<Result<usize, Error>> {
+		run_async(move || Self::update_batch(&rows.into_iter().map(Box::new).collect::<Vec<_>>()))
+	}
+	/// Async version of [`Turbosql::upsert_batch`].
+	fn upsert_batch_async(rows: Vec<Self>) -> DbFuture<Result<Vec<i64>, Error>> {
+		run_async(move || Self::upsert_batch(&rows.into_iter().map(Box::new).collect::<Vec<_>>()))
+	}
+	/// Async version of [`Turbosql::get`].
+	fn get_async(rowid: i64) -> DbFuture<Result<Option<Self>, Error>> {
+		run_async(move || Self::get(rowid))
+	}
+	/// Async version of [`Turbosql::all`].
+	fn all_async() -> DbFuture<Result<Vec<Self>, Error>> {
+		run_async(Self::all)
+	}
+	/// Async version of [`Turbosql::count`].
+	fn count_async() -> DbFuture<Result<i64, Error>> {
+		run_async(Self::count)
+	}
+	/// Async version of [`Turbosql::exists`].
+	fn exists_async(rowid: i64) -> DbFuture<Result<bool, Error>> {
+		run_async(move || Self::exists(rowid))
+	}
+	/// Async version of [`Turbosql::delete_by_rowid`].
+	fn delete_by_rowid_async(rowid: i64) -> DbFuture<Result<usize, Error>> {
+		run_async(move || Self::delete_by_rowid(rowid))
+	}
+}
+
+impl<T: Turbosql + Send + 'static> TurbosqlAsync for T {}
+
+/// Async version of [`select!`](crate::select), which runs on the database thread. Bound parameters are moved into the query, so they must be owned and `Send`.
+#[macro_export]
+macro_rules! select_async {
+	($($tokens:tt)*) => {
+		$crate::run_async(move || $crate::select!($($tokens)*))
+	};
+}
+
+/// Async version of [`execute!`](crate::execute), which runs on the database thread. Bound parameters are moved into the query, so they must be owned and `Send`.
+#[macro_export]
+macro_rules! execute_async {
+	($($tokens:tt)*) => {
+		$crate::run_async(move || $crate::execute!($($tokens)*))
+	};
+}
+
+/// Async version of [`update!`](crate::update), which runs on the database thread. Bound parameters are moved into the query, so they must be owned and `Send`.
+#[macro_export]
+macro_rules! update_async {
+	($($tokens:tt)*) => {
+		$crate::run_async(move || $crate::update!($($tokens)*))
+	};
+}
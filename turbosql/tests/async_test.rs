@@ -0,0 +1,88 @@
+// cargo test --features test,async --manifest-path turbosql/Cargo.toml -- --nocapture --test-threads=1
+
+#[cfg(not(feature = "test"))]
+compile_error!("turbosql must be tested with '--features test -- --test-threads=1'");
+#[cfg(not(test))]
+compile_error!("async_test.rs must be run in test mode");
+
+use turbosql::{execute, select, Turbosql, TurbosqlAsync};
+
+#[derive(Turbosql, Default, Debug, PartialEq, Clone)]
+struct AsyncTest {
+	rowid: Option<i64>,
+	name: Option<String>,
+	n: Option<i64>,
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn async_test() {
+	// async calls share one connection across tests, so start from an empty table
+	turbosql::execute_async!("DELETE FROM asynctest").await.unwrap();
+
+	let rowid =
+		AsyncTest { rowid: None, name: Some("a".into()), n: Some(1) }.insert_async().await.unwrap();
+	assert_eq!(AsyncTest::count_async().await.unwrap(), 1);
+	assert!(AsyncTest::exists_async(rowid).await.unwrap());
+
+	let mut row = AsyncTest::get_async(rowid).await.unwrap().unwrap();
+	assert_eq!(row.name, Some("a".into()));
+	row.n = Some(2);
+	assert_eq!(row.clone().update_async().await.unwrap(), 1);
+
+	let n = 2;
+	assert_eq!(turbosql::select_async!(AsyncTest "WHERE n = $n").await.unwrap(), row);
+	assert_eq!(
+		turbosql::select_async!(i64 "n FROM asynctest WHERE rowid = ?", rowid).await.unwrap(),
+		2
+	);
+
+	turbosql::execute_async!("UPDATE asynctest SET n = n + 1").await.unwrap();
+	assert_eq!(turbosql::update_async!("asynctest SET n = n + 1").await.unwrap(), 1);
+	assert_eq!(turbosql::run_async(|| select!(i64 "n FROM asynctest")).await.unwrap(), 4);
+
+	let rowids =
+		AsyncTest::insert_batch_async(vec![AsyncTest::default(), AsyncTest::default()]).await.unwrap();
+	assert_eq!(rowids.len(), 2);
+	assert_eq!(AsyncTest::all_async().await.unwrap().len(), 3);
+
+	assert_eq!(AsyncTest::delete_by_rowid_async(rowids[0]).await.unwrap(), 1);
+	assert_eq!(row.delete_async().await.unwrap(), 1);
+	assert_eq!(AsyncTest::count_async().await.unwrap(), 1);
+
+	// all async calls share the database thread's connection
+
+	let tasks: Vec<_> = (0..10)
+		.map(|n| tokio::spawn(AsyncTest { rowid: None, name: None, n: Some(n) }.insert_async()))
+		.collect();
+	for task in tasks {
+		task.await.unwrap().unwrap();
+	}
+	assert_eq!(AsyncTest::count_async().await.unwrap(), 11);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn transaction_async_test() {
+	turbosql::transaction_async(|| {
+		AsyncTest { rowid: None, name: Some("t".into()), n: Some(1) }.insert()?;
+		execute!("UPDATE asynctest SET n = 2 WHERE name = 't'")
+	})
+	.await
+	.unwrap();
+	assert_eq!(turbosql::select_async!(i64 "n FROM asynctest WHERE name = 't'").await.unwrap(), 2);
+
+	// roll back on Err
+
+	let result: Result<(), turbosql::Error> = turbosql::transaction_async(|| {
+		execute!("UPDATE asynctest SET n = 3 WHERE name = 't'")?;
+		Err(turbosql::Error::OtherError("abort"))
+	})
+	.await;
+	assert!(result.is_err());
+	assert_eq!(turbosql::select_async!(i64 "n FROM asynctest WHERE name = 't'").await.unwrap(), 2);
+
+	// a panic on the database thread is resumed in the awaiting task, and the thread keeps running
+
+	let result = tokio::spawn(turbosql::run_async(|| panic!("on the database thread"))).await;
+	assert!(result.unwrap_err().is_panic());
+	assert_eq!(turbosql::run_async(|| 1 + 1).await, 2);
+}
@@ -0,0 +1,45 @@
+// cargo test --features test --manifest-path turbosql/Cargo.toml --test config_test
+
+#[cfg(not(feature = "test"))]
+compile_error!("turbosql must be tested with '--features test -- --test-threads=1'");
+#[cfg(not(test))]
+compile_error!("config_test.rs must be run in test mode");
+
+use std::time::Duration;
+use turbosql::select;
+
+#[test]
+fn config_test() {
+	let path =
+		std::env::temp_dir().join(format!("turbosql_config_test_{}.sqlite", std::process::id()));
+
+	turbosql::Config::new()
+		.path(&path)
+		.busy_timeout(Duration::from_secs(5))
+		.cache_size(-4000)
+		.pragma("synchronous", "FULL")
+		.apply()
+		.unwrap();
+
+	let check_pragmas = || {
+		assert_eq!(select!(i64 "timeout FROM pragma_busy_timeout").unwrap(), 5000);
+		assert_eq!(select!(i64 "cache_size FROM pragma_cache_size").unwrap(), -4000);
+		// FULL
+		assert_eq!(select!(i64 "synchronous FROM pragma_synchronous").unwrap(), 2);
+		// default kept
+		assert_eq!(select!(String "journal_mode FROM pragma_journal_mode").unwrap(), "wal");
+	};
+
+	check_pragmas();
+	assert_eq!(turbosql::db_path(), path);
+
+	// connections opened later on other threads use the same config
+	std::thread::spawn(check_pragmas).join().unwrap();
+
+	// too late once the database is open
+	assert!(turbosql::Config::new().path(":memory:").apply().is_err());
+
+	for ext in ["sqlite", "sqlite-wal", "sqlite-shm"] {
+		let _ = std::fs::remove_file(path.with_extension(ext));
+	}
+}
@@ -0,0 +1,96 @@
+// cargo test --features "test chrono time jiff" --manifest-path turbosql/Cargo.toml --test datetime_test -- --test-threads=1
+
+#[cfg(not(feature = "test"))]
+compile_error!("turbosql must be tested with '--features test -- --test-threads=1'");
+#[cfg(not(test))]
+compile_error!("datetime_test.rs must be run in test mode");
+
+use chrono::{DateTime, NaiveDate, Utc};
+use time::OffsetDateTime;
+use turbosql::{execute, select, update, Turbosql, UnixMs};
+
+#[derive(Turbosql, Default, Debug, PartialEq, Clone)]
+struct DateTimeTest {
+	rowid: Option<i64>,
+	#[turbosql(datetime_as = "text")]
+	chrono_text: Option<DateTime<Utc>>,
+	#[turbosql(datetime_as = "unix_ms")]
+	chrono_ms: Option<DateTime<Utc>>,
+	#[turbosql(datetime_as = "text")]
+	chrono_date: Option<NaiveDate>,
+	#[turbosql(datetime_as = "text")]
+	time_text: Option<OffsetDateTime>,
+	#[turbosql(datetime_as = "unix_ms")]
+	time_ms: Option<OffsetDateTime>,
+	#[turbosql(datetime_as = "text")]
+	jiff_text: Option<jiff::Timestamp>,
+	#[turbosql(datetime_as = "unix_ms")]
+	jiff_ms: Option<jiff::Timestamp>,
+	#[turbosql(datetime_as = "unix_ms")]
+	required_ms: DateTime<Utc>,
+	chrono_json: Option<DateTime<Utc>>,
+}
+
+#[test]
+fn datetime_test() {
+	execute!("DELETE FROM datetimetest").unwrap();
+
+	let at = DateTime::from_timestamp_millis(1_700_000_000_123).unwrap();
+	let time_at = OffsetDateTime::from_unix_timestamp_nanos(1_700_000_000_123_000_000).unwrap();
+	let jiff_at = jiff::Timestamp::from_millisecond(1_700_000_000_123).unwrap();
+
+	let row = DateTimeTest {
+		rowid: None,
+		chrono_text: Some(at),
+		chrono_ms: Some(at),
+		chrono_date: NaiveDate::from_ymd_opt(2023, 11, 14),
+		time_text: Some(time_at),
+		time_ms: Some(time_at),
+		jiff_text: Some(jiff_at),
+		jiff_ms: Some(jiff_at),
+		required_ms: at,
+		chrono_json: Some(at),
+	};
+	let rowid = row.insert().unwrap();
+	let row = DateTimeTest { rowid: Some(rowid), ..row };
+
+	assert_eq!(select!(DateTimeTest "WHERE rowid = ?", rowid).unwrap(), row);
+	assert_eq!(DateTimeTest::get(rowid).unwrap(), Some(row.clone()));
+
+	// storage
+	assert_eq!(select!(String "typeof(chrono_text) FROM datetimetest").unwrap(), "text");
+	assert_eq!(select!(i64 "chrono_ms FROM datetimetest").unwrap(), 1_700_000_000_123);
+	assert_eq!(select!(i64 "time_ms FROM datetimetest").unwrap(), 1_700_000_000_123);
+	assert_eq!(select!(i64 "jiff_ms FROM datetimetest").unwrap(), 1_700_000_000_123);
+	assert_eq!(select!(String "chrono_date FROM datetimetest").unwrap(), "2023-11-14");
+	// without datetime_as, as before dates had native columns
+	assert_eq!(
+		select!(String "chrono_json FROM datetimetest").unwrap(),
+		"\"2023-11-14T22:13:20.123Z\""
+	);
+	assert_eq!(select!(DateTimeTest.chrono_json).unwrap(), Some(at));
+
+	// single columns and bound parameters
+	assert_eq!(select!(Option<DateTime<Utc>> "chrono_text FROM datetimetest").unwrap(), Some(at));
+	assert_eq!(
+		select!(Option<UnixMs<DateTime<Utc>>> "chrono_ms FROM datetimetest").unwrap(),
+		Some(UnixMs(at))
+	);
+	assert_eq!(select!(Vec<DateTimeTest.required_ms>).unwrap(), vec![at]);
+	let earlier = at - chrono::Duration::seconds(1);
+	assert_eq!(select!(i64 "COUNT(*) FROM datetimetest WHERE chrono_text > ?", earlier).unwrap(), 1);
+	assert_eq!(
+		select!(i64 "COUNT(*) FROM datetimetest WHERE chrono_ms > ?", UnixMs(earlier)).unwrap(),
+		1
+	);
+	assert_eq!(select!(i64 "COUNT(*) FROM datetimetest WHERE chrono_ms > ?", UnixMs(at)).unwrap(), 0);
+
+	// update and NULL
+	let row = DateTimeTest { chrono_ms: None, time_ms: None, required_ms: earlier, ..row };
+	row.update().unwrap();
+	assert_eq!(DateTimeTest::get(rowid).unwrap(), Some(row.clone()));
+	assert_eq!(select!(String "typeof(chrono_ms) FROM datetimetest").unwrap(), "null");
+
+	update!("datetimetest SET required_ms = ?", UnixMs(at)).unwrap();
+	assert_eq!(select!(DateTimeTest).unwrap().required_ms, at);
+}
@@ -0,0 +1,93 @@
+// cargo test --features test --manifest-path turbosql/Cargo.toml --test enum_test -- --test-threads=1
+
+#[cfg(not(feature = "test"))]
+compile_error!("turbosql must be tested with '--features test -- --test-threads=1'");
+#[cfg(not(test))]
+compile_error!("enum_test.rs must be run in test mode");
+
+use turbosql::{execute, select, Turbosql, TurbosqlEnum};
+
+#[derive(TurbosqlEnum, Default, Debug, PartialEq, Clone, Copy)]
+enum Status {
+	#[default]
+	Active,
+	Archived,
+}
+
+#[derive(TurbosqlEnum, Default, Debug, PartialEq, Clone, Copy)]
+#[turbosql(repr = "int")]
+enum Priority {
+	Low = -1,
+	#[default]
+	Normal,
+	High = 10,
+}
+
+#[derive(Turbosql, Default, Debug, PartialEq, Clone)]
+struct EnumTest {
+	rowid: Option<i64>,
+	#[turbosql(enum_values("Active", "Archived"))]
+	status: Status,
+	#[turbosql(enum_values(-1, 0, 10))]
+	priority: Option<Priority>,
+	#[turbosql(sql_type = "TEXT")]
+	unchecked: Option<Status>,
+}
+
+#[test]
+fn enum_test() {
+	execute!("DELETE FROM enumtest").unwrap();
+
+	assert_eq!(
+		select!(String "sql FROM sqlite_schema WHERE name = 'enumtest'").unwrap(),
+		r#"CREATE TABLE enumtest (rowid INTEGER PRIMARY KEY, status TEXT NOT NULL CONSTRAINT "enumtest: status IN ('Active', 'Archived')" CHECK (status IN ('Active', 'Archived')) DEFAULT 'Active', priority INTEGER CONSTRAINT "enumtest: priority IN (-1, 0, 10)" CHECK (priority IN (-1, 0, 10)), unchecked TEXT) STRICT"#
+	);
+
+	let row = EnumTest {
+		rowid: None,
+		status: Status::Archived,
+		priority: Some(Priority::High),
+		unchecked: Some(Status::Archived),
+	};
+	let rowid = row.insert().unwrap();
+	let row = EnumTest { rowid: Some(rowid), ..row };
+	assert_eq!(EnumTest::get(rowid).unwrap(), Some(row.clone()));
+
+	let low =
+		EnumTest { rowid: None, status: Status::Active, priority: Some(Priority::Low), unchecked: None };
+	let low = EnumTest { rowid: Some(low.insert().unwrap()), ..low };
+	let none = EnumTest { rowid: None, priority: None, ..Default::default() };
+	let none = EnumTest { rowid: Some(none.insert().unwrap()), ..none };
+
+	// storage
+	assert_eq!(select!(String "status FROM enumtest WHERE rowid = ?", rowid).unwrap(), "Archived");
+	assert_eq!(select!(i64 "priority FROM enumtest WHERE rowid = ?", rowid).unwrap(), 10);
+	assert_eq!(select!(i64 "priority FROM enumtest WHERE rowid = ?", low.rowid).unwrap(), -1);
+	assert_eq!(select!(String "unchecked FROM enumtest WHERE rowid = ?", rowid).unwrap(), "Archived");
+
+	// enum values bind as parameters
+	assert_eq!(
+		select!(Vec<EnumTest> "WHERE status = ?", Status::Archived).unwrap(),
+		vec![row.clone()]
+	);
+	assert_eq!(
+		select!(Vec<EnumTest> "WHERE priority < ?", Priority::Normal).unwrap(),
+		vec![low.clone()]
+	);
+	assert_eq!(select!(Vec<EnumTest> "WHERE priority IS NULL").unwrap(), vec![none]);
+	assert_eq!(
+		select!(Vec<EnumTest.status> "ORDER BY rowid").unwrap(),
+		vec![Status::Archived, Status::Active, Status::Active]
+	);
+
+	// CHECK rejects values that aren't variants
+	let e = execute!("UPDATE enumtest SET status = 'Deleted'").unwrap_err();
+	assert!(
+		matches!(e, turbosql::Error::CheckViolation { ref table, ref constraint } if table == "enumtest" && constraint == "status IN ('Active', 'Archived')"),
+		"{:?}",
+		e
+	);
+	let e = execute!("UPDATE enumtest SET priority = 1").unwrap_err();
+	assert!(matches!(e, turbosql::Error::CheckViolation { .. }), "{:?}", e);
+	assert_eq!(EnumTest::get(rowid).unwrap(), Some(row));
+}
@@ -0,0 +1,65 @@
+// cargo test --features "test i128_blob" --manifest-path turbosql/Cargo.toml --test integer_test -- --test-threads=1
+
+#[cfg(not(feature = "test"))]
+compile_error!("turbosql must be tested with '--features test -- --test-threads=1'");
+#[cfg(not(test))]
+compile_error!("integer_test.rs must be run in test mode");
+
+use turbosql::{execute, select, Blob, Turbosql, U64BlobBe, U64Text};
+
+#[derive(Turbosql, Default, Debug, PartialEq, Clone)]
+struct IntegerTest {
+	rowid: Option<i64>,
+	#[turbosql(u64_as = "blob_be")]
+	hash: Option<u64>,
+	#[turbosql(u64_as = "checked_i64")]
+	counter: Option<u64>,
+	#[turbosql(u64_as = "text")]
+	text: Option<u64>,
+	#[turbosql(u64_as = "blob_be")]
+	required_hash: u64,
+	big: Option<i128>,
+}
+
+#[test]
+fn integer_test() {
+	execute!("DELETE FROM integertest").unwrap();
+
+	let values = [0, 1, 255, 256, i64::MAX as u64, i64::MAX as u64 + 1, u64::MAX];
+	for (i, &value) in values.iter().enumerate() {
+		let row = IntegerTest {
+			rowid: None,
+			hash: Some(value),
+			counter: Some(value.min(i64::MAX as u64)),
+			text: Some(value),
+			required_hash: value,
+			big: Some(i128::from(value) * if i % 2 == 0 { 1 } else { -1 }),
+		};
+		let rowid = row.insert().unwrap();
+		assert_eq!(IntegerTest::get(rowid).unwrap(), Some(IntegerTest { rowid: Some(rowid), ..row }));
+	}
+
+	// storage
+	assert_eq!(
+		select!(Blob "hash FROM integertest WHERE text = '256'").unwrap(),
+		[0, 0, 0, 0, 0, 0, 1, 0]
+	);
+	assert_eq!(select!(i64 "counter FROM integertest WHERE text = '256'").unwrap(), 256);
+
+	// big-endian blobs and i128s sort numerically
+	assert_eq!(select!(Vec<IntegerTest.hash> "ORDER BY hash").unwrap(), values.map(Some));
+	let mut big = select!(Vec<IntegerTest.big>).unwrap();
+	big.sort();
+	assert_eq!(select!(Vec<IntegerTest.big> "ORDER BY big").unwrap(), big);
+
+	// bound parameters
+	let above = select!(Vec<U64BlobBe<u64>> "hash FROM integertest WHERE hash > ? ORDER BY hash", U64BlobBe(i64::MAX as u64))
+		.unwrap();
+	assert_eq!(above, [U64BlobBe(i64::MAX as u64 + 1), U64BlobBe(u64::MAX)]);
+	assert_eq!(select!(u64 "counter FROM integertest WHERE text = ?", U64Text(255u64)).unwrap(), 255);
+	assert_eq!(select!(i128 "big FROM integertest WHERE big = ?", -1i128).unwrap(), -1);
+
+	// checked_i64 refuses values it can't store
+	let row = IntegerTest { counter: Some(u64::MAX), ..Default::default() };
+	assert!(row.insert().is_err());
+}
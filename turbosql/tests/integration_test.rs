@@ -7,7 +7,7 @@ compile_error!("turbosql must be tested with '--features test -- --test-threads=
 #[cfg(not(test))]
 compile_error!("integration_tests.rs must be run in test mode");
 
-use turbosql::{execute, select, update, Blob, Turbosql};
+use turbosql::{execute, select, update, Blob, Ref, Turbosql};
 
 #[derive(Turbosql, Default, Debug, PartialEq, Clone)]
 struct PersonIntegrationTest {
@@ -60,6 +60,7 @@ struct PersonIntegrationTest {
 }
 
 #[test]
+#[allow(clippy::format_in_format_args)]
 fn integration_test() {
 	let mut row = PersonIntegrationTest {
 		rowid: None,
@@ -191,7 +192,9 @@ fn integration_test() {
 	assert_eq!(select!(Option<PersonIntegrationTest>).unwrap(), Some(row.clone()));
 
 	let field_u8 = row.field_u8;
-	// assert_eq!(select!(PersonIntegrationTest r#"WHERE field_u8 = $field_u8"#).unwrap(), row);
+	assert_eq!(select!(PersonIntegrationTest r#"WHERE field_u8 = $field_u8"#).unwrap(), row);
+	assert_eq!(select!(PersonIntegrationTest "WHERE field_u8 = :field_u8").unwrap(), row);
+	assert_eq!(select!(PersonIntegrationTest "WHERE field_u8 = @field_u8").unwrap(), row);
 	assert_eq!(select!(PersonIntegrationTest "WHERE field_u8 = " field_u8).unwrap(), row);
 	assert_eq!(
 		select!(PersonIntegrationTest "WHERE field_u8 = " field_u8 " AND 1 = " 1).unwrap(),
@@ -200,16 +203,33 @@ fn integration_test() {
 	assert!(select!(PersonIntegrationTest "WHERE field_u8 = " field_u8 " AND 1 = " 0).is_err());
 
 	let field_string = row.field_string.clone();
-	// assert_eq!(select!(PersonIntegrationTest r#"WHERE field_string = $field_string"#).unwrap(), row);
-	// assert_eq!(select!(PersonIntegrationTest r#"WHERE field_string = $field_string"#).unwrap(), row);
+	assert_eq!(select!(PersonIntegrationTest r#"WHERE field_string = $field_string"#).unwrap(), row);
+	assert_eq!(
+		select!(PersonIntegrationTest "WHERE field_string = $field_string AND field_u8 = $field_u8")
+			.unwrap(),
+		row
+	);
+	assert_eq!(
+		select!(PersonIntegrationTest "WHERE field_string = $field_string OR $field_string IS NULL")
+			.unwrap(),
+		row
+	);
 	assert_eq!(select!(PersonIntegrationTest "WHERE field_string = " field_string " ").unwrap(), row);
 	assert_eq!(select!(PersonIntegrationTest "WHERE field_string = " field_string).unwrap(), row);
 
-	// assert_eq!(
-	//  select!(PersonIntegrationTest "WHERE field_u8 = $field_u8 AND 1 = ?", 1, 3, 4).unwrap(), row
-	// );
-
-	// assert_eq!(select!(PersonIntegrationTest "WHERE 1 = ? AND field_u8 = $field_u8", 1).unwrap(), row);
+	assert_eq!(select!(PersonIntegrationTest "WHERE field_u8 = $field_u8 AND 1 = ?", 1).unwrap(), row);
+	assert_eq!(select!(PersonIntegrationTest "WHERE 1 = ? AND field_u8 = $field_u8", 1).unwrap(), row);
+	assert_eq!(
+		select!(PersonIntegrationTest "WHERE 1 = ? AND field_u8 = $field_u8 AND 2 = ?", 1, 2).unwrap(),
+		row
+	);
+	assert_eq!(
+		select!(PersonIntegrationTest "WHERE field_u8 = " field_u8 " AND field_string = $field_string")
+			.unwrap(),
+		row
+	);
+	assert_eq!(execute!("UPDATE personintegrationtest SET field_u8 = $field_u8").unwrap(), 1);
+	assert_eq!(update!("personintegrationtest SET field_u8 = $field_u8 WHERE 1 = ?", 1).unwrap(), 1);
 
 	assert_eq!(
 		select!(PersonIntegrationTest "WHERE field_string = ?", row.field_string.as_ref().unwrap())
@@ -325,32 +345,70 @@ fn integration_test() {
 		row.field_string.as_ref().unwrap()
 	);
 
-	// future: tuples:
-	// let result = select!((String, i64) "name, age FROM person")?;
-	// let result = select!(Vec<(String, i64)> "name, age FROM person")?;
-	// let result = select!(Option<(String, i64)> "name, age FROM person")?;
+	// tuples
+
+	let name_and_age = (row.field_string.clone().unwrap(), row.field_u8.unwrap() as i64);
+	assert_eq!(
+		select!((String, i64) "field_string, field_u8 FROM personintegrationtest").unwrap(),
+		name_and_age
+	);
+	assert_eq!(
+		select!(Vec<(String, i64)> "field_string, field_u8 FROM personintegrationtest").unwrap(),
+		vec![name_and_age.clone()]
+	);
+	assert_eq!(
+		select!(Option<(String, i64)> "field_string, field_u8 FROM personintegrationtest").unwrap(),
+		Some(name_and_age.clone())
+	);
+	assert_eq!(
+		select!(Option<(String, i64)> "SELECT field_string, field_u8 FROM personintegrationtest WHERE 0")
+			.unwrap(),
+		None
+	);
+	assert_eq!(
+		select!((i64, Option<String>, bool) "rowid, field_string, field_u8 = " field_u8 " FROM personintegrationtest")
+			.unwrap(),
+		(row.rowid.unwrap(), row.field_string.clone(), true)
+	);
 
 	// struct members
-	// assert_eq!(select!(Vec<PersonIntegrationTest.field_u8>).unwrap(), vec![row.field_u8]);
-	// assert_eq!(select!(Option<PersonIntegrationTest.field_u8>).unwrap(), Some(row.field_u8));
-	// assert_eq!(select!(PersonIntegrationTest.field_u8).unwrap(), row.field_u8);
-	// assert_eq!(
-	//  select!(Vec<PersonIntegrationTest.field_vec_u8>).unwrap(),
-	//  vec![row.field_vec_u8.clone()]
-	// );
-	// assert_eq!(
-	//  select!(Option<PersonIntegrationTest.field_vec_u8>).unwrap(),
-	//  Some(row.field_vec_u8.clone())
-	// );
-	// assert_eq!(select!(PersonIntegrationTest.field_vec_u8).unwrap(), row.field_vec_u8.clone());
-	// assert_eq!(select!(Vec<PersonIntegrationTest.field_array_u8>).unwrap(), vec![row.field_array_u8]);
-	// assert_eq!(
-	//  select!(Option<PersonIntegrationTest.field_array_u8>).unwrap(),
-	//  Some(row.field_array_u8)
-	// );
-	// assert_eq!(select!(PersonIntegrationTest.field_array_u8).unwrap(), row.field_array_u8);
-
-	// assert_eq!(select!(Option<PersonIntegrationTest.field_u8> "WHERE 0").unwrap(), None);
+
+	assert_eq!(select!(Vec<PersonIntegrationTest.field_u8>).unwrap(), vec![row.field_u8]);
+	assert_eq!(select!(Option<PersonIntegrationTest.field_u8>).unwrap(), Some(row.field_u8));
+	assert_eq!(select!(PersonIntegrationTest.field_u8).unwrap(), row.field_u8);
+	assert_eq!(
+		select!(Vec<PersonIntegrationTest.field_vec_u8>).unwrap(),
+		vec![row.field_vec_u8.clone()]
+	);
+	assert_eq!(
+		select!(Option<PersonIntegrationTest.field_vec_u8>).unwrap(),
+		Some(row.field_vec_u8.clone())
+	);
+	assert_eq!(select!(PersonIntegrationTest.field_vec_u8).unwrap(), row.field_vec_u8.clone());
+	assert_eq!(select!(Vec<PersonIntegrationTest.field_array_u8>).unwrap(), vec![row.field_array_u8]);
+	assert_eq!(
+		select!(Option<PersonIntegrationTest.field_array_u8>).unwrap(),
+		Some(row.field_array_u8)
+	);
+	assert_eq!(select!(PersonIntegrationTest.field_array_u8).unwrap(), row.field_array_u8);
+
+	assert_eq!(select!(Option<PersonIntegrationTest.field_u8> "WHERE 0").unwrap(), None);
+	assert_eq!(
+		select!(PersonIntegrationTest.field_string "WHERE field_u8 = $field_u8").unwrap(),
+		row.field_string
+	);
+	assert_eq!(
+		select!(crate::PersonIntegrationTest.field_i64_not_null).unwrap(),
+		row.field_i64_not_null
+	);
+
+	// JSON-serialized fields are deserialized
+
+	assert_eq!(select!(PersonIntegrationTest.field_serialize).unwrap(), row.field_serialize);
+	assert_eq!(
+		select!(Vec<PersonIntegrationTest.field_serialize>).unwrap(),
+		vec![row.field_serialize.clone()]
+	);
 
 	// let result = select!((Person.name, Person.age))?;
 	// let result = select!({Person.name, Person.age})?;
@@ -374,3 +432,601 @@ fn integration_test() {
 	assert!(execute!("DELETE FROM personintegrationtest").is_ok());
 	assert!(select!(PersonIntegrationTest).is_err());
 }
+
+#[derive(Turbosql, Default, Debug, PartialEq, Clone)]
+struct TransactionTest {
+	rowid: Option<i64>,
+	n: Option<i64>,
+}
+
+#[test]
+fn transaction_test() {
+	// commit on Ok
+
+	let rowid =
+		turbosql::transaction(|| TransactionTest { rowid: None, n: Some(1) }.insert()).unwrap();
+	assert_eq!(select!(i64 "n FROM transactiontest WHERE rowid = ?", rowid).unwrap(), 1);
+
+	// roll back on Err
+
+	let result: Result<(), turbosql::Error> = turbosql::transaction_immediate(|| {
+		TransactionTest { rowid: None, n: Some(2) }.insert()?;
+		Err(turbosql::Error::OtherError("abort"))
+	});
+	assert!(result.is_err());
+	assert_eq!(select!(i64 "COUNT(*) FROM transactiontest").unwrap(), 1);
+
+	// roll back on panic
+
+	let result = std::panic::catch_unwind(|| {
+		turbosql::transaction_exclusive(|| -> Result<(), turbosql::Error> {
+			TransactionTest { rowid: None, n: Some(3) }.insert()?;
+			panic!("inside transaction");
+		})
+	});
+	assert!(result.is_err());
+	assert_eq!(select!(i64 "COUNT(*) FROM transactiontest").unwrap(), 1);
+
+	// connection is usable again after a rollback
+
+	turbosql::transaction(|| execute!("UPDATE transactiontest SET n = 4")).unwrap();
+	assert_eq!(select!(Vec<i64> "n FROM transactiontest").unwrap(), vec![4]);
+
+	execute!("DELETE FROM transactiontest").unwrap();
+}
+
+#[test]
+fn nested_transaction_test() {
+	let insert = |n| TransactionTest { rowid: None, n: Some(n) }.insert();
+
+	turbosql::transaction(|| {
+		insert(1)?;
+
+		// inner Ok is kept
+		turbosql::transaction(|| insert(2))?;
+
+		// inner Err only undoes its own work
+		let result: Result<(), turbosql::Error> = turbosql::transaction(|| {
+			insert(3)?;
+			turbosql::transaction(|| insert(4))?;
+			Err(turbosql::Error::OtherError("abort inner"))
+		});
+		assert!(result.is_err());
+
+		// inner panic only undoes its own work
+		let result = std::panic::catch_unwind(|| {
+			turbosql::transaction_immediate(|| -> Result<(), turbosql::Error> {
+				insert(5)?;
+				panic!("inside nested transaction");
+			})
+		});
+		assert!(result.is_err());
+
+		insert(6)
+	})
+	.unwrap();
+
+	assert_eq!(select!(Vec<i64> "n FROM transactiontest ORDER BY n").unwrap(), vec![1, 2, 6]);
+
+	// outer Err undoes everything, including released inner scopes
+
+	let result: Result<(), turbosql::Error> = turbosql::transaction(|| {
+		turbosql::transaction(|| insert(7))?;
+		Err(turbosql::Error::OtherError("abort outer"))
+	});
+	assert!(result.is_err());
+	assert_eq!(select!(Vec<i64> "n FROM transactiontest ORDER BY n").unwrap(), vec![1, 2, 6]);
+
+	execute!("DELETE FROM transactiontest").unwrap();
+}
+
+#[test]
+fn upsert_test() {
+	let mut row = PersonIntegrationTest {
+		field_string: Some("Upsert".into()),
+		field_serialize: Some(vec![1, 2]),
+		..Default::default()
+	};
+
+	// rowid None inserts
+
+	let rowid = row.upsert().unwrap();
+	row.rowid = Some(rowid);
+	assert_eq!(select!(PersonIntegrationTest "WHERE rowid = ?", rowid).unwrap(), row);
+
+	// existing rowid overwrites
+
+	row.field_string = Some("Upserted".into());
+	row.field_serialize = Some(vec![3]);
+	assert_eq!(row.upsert().unwrap(), rowid);
+	assert_eq!(select!(Vec<PersonIntegrationTest>).unwrap(), vec![row.clone()]);
+
+	// missing rowid inserts with that rowid
+
+	let mut row2 = row.clone();
+	row2.rowid = Some(rowid + 100);
+	assert_eq!(row2.upsert().unwrap(), rowid + 100);
+	assert_eq!(select!(PersonIntegrationTest "WHERE rowid = ?", rowid + 100).unwrap(), row2);
+
+	// batch
+
+	row.field_u8 = Some(7);
+	row2.field_u8 = Some(8);
+	let row3 = PersonIntegrationTest { field_u8: Some(9), ..Default::default() };
+	let rowids = PersonIntegrationTest::upsert_batch(&[
+		Box::new(row.clone()),
+		Box::new(row2.clone()),
+		Box::new(row3),
+	])
+	.unwrap();
+	assert_eq!(rowids[..2], [rowid, rowid + 100]);
+	assert_eq!(
+		select!(Vec<u8> "field_u8 FROM personintegrationtest ORDER BY rowid").unwrap(),
+		vec![7, 8, 9]
+	);
+
+	execute!("DELETE FROM personintegrationtest").unwrap();
+}
+
+#[derive(Turbosql, Default, Debug, PartialEq, Clone)]
+#[turbosql(unique(source, source_id))]
+struct UniqueTest {
+	rowid: Option<i64>,
+	#[turbosql(unique)]
+	external_id: Option<String>,
+	source: Option<String>,
+	source_id: Option<i64>,
+	tags: Option<Vec<String>>,
+}
+
+#[test]
+fn upsert_on_unique_test() {
+	let mut row = UniqueTest {
+		rowid: None,
+		external_id: Some("ext-1".into()),
+		source: Some("a".into()),
+		source_id: Some(1),
+		tags: Some(vec!["x".into()]),
+	};
+
+	let rowid = row.upsert_on_external_id().unwrap();
+
+	row.tags = Some(vec!["y".into()]);
+	row.source_id = Some(2);
+	assert_eq!(row.upsert_on_external_id().unwrap(), rowid);
+	row.rowid = Some(rowid);
+	assert_eq!(select!(Vec<UniqueTest>).unwrap(), vec![row.clone()]);
+
+	row.external_id = Some("ext-2".into());
+	assert_eq!(row.upsert_on_source_source_id().unwrap(), rowid);
+	assert_eq!(select!(Vec<UniqueTest>).unwrap(), vec![row.clone()]);
+
+	row.source_id = Some(3);
+	row.external_id = Some("ext-3".into());
+	let rowid2 = row.upsert_on_source_source_id().unwrap();
+	assert_ne!(rowid, rowid2);
+	assert_eq!(select!(i64 "COUNT(*) FROM uniquetest").unwrap(), 2);
+
+	// plain insert still enforces the unique index
+	assert!(UniqueTest { external_id: Some("ext-3".into()), ..Default::default() }.insert().is_err());
+
+	execute!("DELETE FROM uniquetest").unwrap();
+}
+
+#[test]
+fn batch_test() {
+	execute!("DELETE FROM personintegrationtest").unwrap();
+
+	// enough rows to need several multi-row INSERT statements
+
+	let rows = (0..5000)
+		.map(|i| {
+			Box::new(PersonIntegrationTest {
+				field_i64: Some(i),
+				field_serialize: Some(vec![i]),
+				..Default::default()
+			})
+		})
+		.collect::<Vec<_>>();
+
+	let rowids = PersonIntegrationTest::insert_batch(&rows).unwrap();
+	assert_eq!(rowids, select!(Vec<i64> "rowid FROM personintegrationtest ORDER BY rowid").unwrap());
+	assert_eq!(
+		select!(Vec<i64> "field_i64 FROM personintegrationtest ORDER BY rowid").unwrap(),
+		(0..5000).collect::<Vec<_>>()
+	);
+	// rowids are returned in the order of the input rows
+	let rowid = rowids[4320];
+	assert_eq!(
+		select!(PersonIntegrationTest "WHERE rowid = " rowid).unwrap().field_serialize,
+		Some(vec![4320])
+	);
+
+	assert!(PersonIntegrationTest::insert_batch(&[] as &[Box<PersonIntegrationTest>])
+		.unwrap()
+		.is_empty());
+
+	// update_batch returns the total number of rows updated
+
+	let rows = select!(Vec<PersonIntegrationTest> "ORDER BY rowid LIMIT 3")
+		.unwrap()
+		.into_iter()
+		.map(|row| Box::new(PersonIntegrationTest { field_u8: Some(1), ..row }))
+		.collect::<Vec<_>>();
+	assert_eq!(PersonIntegrationTest::update_batch(&rows).unwrap(), 3);
+	assert_eq!(select!(i64 "COUNT(*) FROM personintegrationtest WHERE field_u8 = 1").unwrap(), 3);
+
+	// once rowid i64::MAX is taken, new rowids are random, and still returned in the order of the input rows
+
+	execute!("DELETE FROM personintegrationtest").unwrap();
+	let rowid = PersonIntegrationTest::default().insert().unwrap();
+	let max = i64::MAX;
+	execute!("UPDATE personintegrationtest SET rowid = " max " WHERE rowid = " rowid).unwrap();
+	let rows = (0..100)
+		.map(|i| Box::new(PersonIntegrationTest { field_i64: Some(i), ..Default::default() }))
+		.collect::<Vec<_>>();
+	let rowids = PersonIntegrationTest::insert_batch(&rows).unwrap();
+	for (i, rowid) in rowids.into_iter().enumerate() {
+		assert_eq!(
+			select!(i64 "field_i64 FROM personintegrationtest WHERE rowid = " rowid).unwrap(),
+			i as i64
+		);
+	}
+	execute!("DELETE FROM personintegrationtest").unwrap();
+
+	// a failing row rolls back the whole batch
+
+	let dupes = ["a", "b", "a"]
+		.iter()
+		.map(|id| Box::new(UniqueTest { external_id: Some(id.to_string()), ..Default::default() }))
+		.collect::<Vec<_>>();
+	assert!(UniqueTest::insert_batch(&dupes).is_err());
+	assert_eq!(select!(i64 "COUNT(*) FROM uniquetest").unwrap(), 0);
+
+	let rowids = UniqueTest::insert_batch(&dupes[..2]).unwrap();
+	let rows = rowids
+		.iter()
+		.map(|&rowid| {
+			Box::new(UniqueTest { rowid: Some(rowid), external_id: Some("c".into()), ..Default::default() })
+		})
+		.collect::<Vec<_>>();
+	assert!(UniqueTest::update_batch(&rows).is_err());
+	assert_eq!(select!(Vec<String> "external_id FROM uniquetest ORDER BY rowid").unwrap(), ["a", "b"]);
+
+	execute!("DELETE FROM uniquetest").unwrap();
+}
+
+#[derive(Turbosql, Default, Debug, PartialEq, Clone)]
+struct FinderTest {
+	rowid: Option<i64>,
+	name: Option<String>,
+	tags: Vec<String>,
+	#[turbosql(skip)]
+	cached: Option<i64>,
+}
+
+#[test]
+fn finder_test() {
+	assert_eq!(FinderTest::count().unwrap(), 0);
+	assert_eq!(FinderTest::all().unwrap(), vec![]);
+	assert_eq!(FinderTest::get(1).unwrap(), None);
+	assert!(!FinderTest::exists(1).unwrap());
+
+	let mut row =
+		FinderTest { rowid: None, name: Some("a".into()), tags: vec!["x".into()], cached: Some(1) };
+	let rowid = row.insert().unwrap();
+	let rowid2 = FinderTest { name: Some("b".into()), ..Default::default() }.insert().unwrap();
+
+	row.rowid = Some(rowid);
+	row.cached = None;
+	assert_eq!(FinderTest::get(rowid).unwrap(), Some(row.clone()));
+	assert_eq!(FinderTest::count().unwrap(), 2);
+	assert!(FinderTest::exists(rowid2).unwrap());
+	assert_eq!(FinderTest::all().unwrap()[0], row);
+
+	assert_eq!(FinderTest::delete_by_rowid(rowid2).unwrap(), 1);
+	assert_eq!(FinderTest::delete_by_rowid(rowid2).unwrap(), 0);
+	assert!(!FinderTest::exists(rowid2).unwrap());
+	assert_eq!(FinderTest::all().unwrap(), vec![row]);
+
+	execute!("DELETE FROM findertest").unwrap();
+}
+
+#[test]
+fn streaming_test() {
+	let rows =
+		(0..100).map(|n| Box::new(TransactionTest { rowid: None, n: Some(n) })).collect::<Vec<_>>();
+	TransactionTest::insert_batch(&rows).unwrap();
+
+	let mut sum = 0;
+	select!(Iter<TransactionTest>)
+		.for_each(|row| {
+			sum += row.n.unwrap();
+			Ok(())
+		})
+		.unwrap();
+	assert_eq!(sum, 4950);
+
+	// lazily stops reading after the rows that are consumed
+	let first = select!(Iter<i64> "n FROM transactiontest WHERE n >= ? ORDER BY n", 10)
+		.with(|rows| rows.take(3).collect::<Result<Vec<_>, _>>())
+		.unwrap()
+		.unwrap();
+	assert_eq!(first, vec![10, 11, 12]);
+
+	assert_eq!(select!(Iter<TransactionTest.n> "WHERE n < 5").with(|rows| rows.count()).unwrap(), 5);
+
+	// other queries can run while streaming
+	select!(Iter<TransactionTest> "WHERE n < 10")
+		.for_each(|row| {
+			execute!("UPDATE transactiontest SET n = n + 1000 WHERE rowid = ?", row.rowid)?;
+			Ok(())
+		})
+		.unwrap();
+	assert_eq!(select!(i64 "COUNT(*) FROM transactiontest WHERE n >= 1000").unwrap(), 10);
+
+	// errors from the callback stop iteration
+	let mut seen = 0;
+	let result = select!(Iter<TransactionTest>).for_each(|_| {
+		seen += 1;
+		Err(turbosql::Error::OtherError("stop"))
+	});
+	assert!(result.is_err());
+	assert_eq!(seen, 1);
+
+	// row conversion errors are reported rather than silently dropped
+	execute!("INSERT INTO transactiontest (n) VALUES (NULL)").unwrap();
+	let results = select!(Iter<i64> "n FROM transactiontest ORDER BY n IS NULL DESC")
+		.with(|rows| rows.collect::<Vec<_>>())
+		.unwrap();
+	assert!(results[0].is_err());
+	assert_eq!(results.len(), 101);
+	assert!(select!(Vec<i64> "n FROM transactiontest").is_err());
+	assert!(select!(i64 "n FROM transactiontest WHERE n IS NULL").is_err());
+
+	execute!("DELETE FROM transactiontest").unwrap();
+}
+
+#[derive(Turbosql, Default, Debug, PartialEq, Clone)]
+#[turbosql(db = "cache")]
+struct CacheTest {
+	rowid: Option<i64>,
+	key: Option<String>,
+	value: Option<Vec<u8>>,
+}
+
+#[test]
+fn named_db_test() {
+	let cache = turbosql::db("cache");
+	assert_eq!(cache.path(), std::path::Path::new(":memory:"));
+
+	let rowid =
+		CacheTest { rowid: None, key: Some("a".into()), value: Some(vec![1]) }.insert().unwrap();
+	assert_eq!(CacheTest::get(rowid).unwrap().unwrap().value, Some(vec![1]));
+
+	let key = "a";
+	assert_eq!(select!(db = "cache", Vec<CacheTest> "WHERE key = $key").unwrap().len(), 1);
+	assert_eq!(
+		select!(db = "cache", CacheTest.value "WHERE rowid = ?", rowid).unwrap(),
+		Some(vec![1])
+	);
+	assert_eq!(select!(db = "cache", i64 "COUNT(*) FROM cachetest").unwrap(), 1);
+	assert_eq!(execute!(db = "cache", "UPDATE cachetest SET value = x'02'").unwrap(), 1);
+	assert_eq!(update!(db = "cache", "cachetest SET key = 'b'").unwrap(), 1);
+	assert_eq!(
+		select!(db = "cache", (String, Vec<u8>) "key, value FROM cachetest").unwrap(),
+		("b".into(), vec![2])
+	);
+
+	// transactions and batches are on the named database's connection
+
+	let result: Result<(), turbosql::Error> = cache.transaction(|| {
+		CacheTest::insert_batch(&[Box::new(CacheTest::default()), Box::new(CacheTest::default())])?;
+		assert_eq!(CacheTest::count()?, 3);
+		Err(turbosql::Error::OtherError("abort"))
+	});
+	assert!(result.is_err());
+	assert_eq!(CacheTest::count().unwrap(), 1);
+
+	// a named database's connection is per-thread, like the main one
+	std::thread::spawn(|| assert_eq!(CacheTest::count().unwrap(), 0)).join().unwrap();
+}
+
+#[derive(Turbosql, Default, Debug, PartialEq, Clone)]
+struct RenameTest {
+	rowid: Option<i64>,
+	#[turbosql(renamed_from = "label")]
+	title: Option<String>,
+}
+
+#[test]
+fn rename_test() {
+	// `title` was added as `label`, then renamed, so it has a RENAME COLUMN migration rather than its own ADD COLUMN
+	let rowid = RenameTest { rowid: None, title: Some("a".into()) }.insert().unwrap();
+	assert_eq!(select!(RenameTest.title "WHERE rowid = ?", rowid).unwrap(), Some("a".into()));
+	assert_eq!(
+		select!(Vec<String> "name FROM pragma_table_info('renametest')").unwrap(),
+		["rowid", "title"]
+	);
+}
+
+#[derive(Turbosql, Default, Debug, PartialEq, Clone)]
+#[turbosql(allow_rebuild)]
+struct RebuildTest {
+	rowid: Option<i64>,
+	count: i64,
+	kept: Option<f64>,
+}
+
+#[test]
+fn rebuild_test() {
+	// `count` was TEXT and `note` existed when the table was first compiled, so the table is rebuilt
+	let rowid = RebuildTest { rowid: None, count: 3, kept: Some(1.5) }.insert().unwrap();
+	assert_eq!(
+		select!(RebuildTest "WHERE rowid = ?", rowid).unwrap(),
+		RebuildTest { rowid: Some(rowid), count: 3, kept: Some(1.5) }
+	);
+	assert_eq!(
+		select!(Vec<String> "name || ' ' || type FROM pragma_table_info('rebuildtest')").unwrap(),
+		["rowid INTEGER", "count INTEGER", "kept REAL"]
+	);
+	assert_eq!(
+		select!(String "sql FROM sqlite_schema WHERE name = 'rebuildtest'").unwrap(),
+		"CREATE TABLE \"rebuildtest\" (rowid INTEGER PRIMARY KEY, count INTEGER NOT NULL DEFAULT 0, kept REAL) STRICT"
+	);
+}
+
+#[derive(Turbosql, Default, Debug, PartialEq, Clone)]
+#[turbosql(index(author, published), unique_index(isbn))]
+struct IndexTest {
+	rowid: Option<i64>,
+	#[turbosql(index)]
+	title: Option<String>,
+	author: Option<String>,
+	published: Option<i64>,
+	isbn: Option<String>,
+}
+
+#[test]
+fn index_test() {
+	assert_eq!(
+		select!(Vec<String> "sql FROM sqlite_schema WHERE type = 'index' AND tbl_name = 'indextest' ORDER BY name").unwrap(),
+		[
+			"CREATE INDEX indextest_author_published_index ON indextest (author, published)",
+			"CREATE UNIQUE INDEX indextest_isbn_unique ON indextest (isbn)",
+			"CREATE INDEX indextest_title_index ON indextest (title)",
+		]
+	);
+
+	// unique_index(...) is the same as unique(...), so it gets an upsert_on_ method
+	let book = IndexTest { isbn: Some("978-0".into()), title: Some("a".into()), ..Default::default() };
+	let rowid = book.upsert_on_isbn().unwrap();
+	assert_eq!(IndexTest { title: Some("b".into()), ..book.clone() }.upsert_on_isbn().unwrap(), rowid);
+	assert!(book.insert().is_err());
+	assert_eq!(select!(Vec<IndexTest.title>).unwrap(), [Some("b".to_string())]);
+}
+
+#[derive(Turbosql, Default, Debug, PartialEq, Clone)]
+struct RefParent {
+	rowid: Option<i64>,
+	name: Option<String>,
+}
+
+#[derive(Turbosql, Default, Debug, PartialEq, Clone)]
+struct RefChild {
+	rowid: Option<i64>,
+	#[turbosql(on_delete = "cascade")]
+	parent: Option<Ref<RefParent>>,
+	#[turbosql(references = RefParent, on_delete = "set null")]
+	sibling_of: Option<i64>,
+}
+
+#[test]
+fn references_test() {
+	assert_eq!(
+		select!(String "sql FROM sqlite_schema WHERE name = 'refchild'").unwrap(),
+		"CREATE TABLE refchild (rowid INTEGER PRIMARY KEY, parent INTEGER REFERENCES refparent(rowid) ON DELETE CASCADE, sibling_of INTEGER REFERENCES refparent(rowid) ON DELETE SET NULL) STRICT"
+	);
+
+	let alice = RefParent { rowid: None, name: Some("Alice".into()) }.insert().unwrap();
+	let bob = RefParent { rowid: None, name: Some("Bob".into()) }.insert().unwrap();
+
+	let child = RefChild { rowid: None, parent: Some(Ref::new(alice)), sibling_of: Some(bob) };
+	let child_rowid = child.insert().unwrap();
+	assert_eq!(child.parent.unwrap().load().unwrap().unwrap().name, Some("Alice".into()));
+	assert_eq!(
+		select!(RefChild "WHERE rowid = ?", child_rowid).unwrap().parent,
+		Some(Ref::new(alice))
+	);
+
+	// foreign keys are enforced
+	assert!(RefChild { rowid: None, parent: Some(Ref::new(-1)), sibling_of: None }.insert().is_err());
+	assert!(RefChild { rowid: None, parent: None, sibling_of: Some(-1) }.insert().is_err());
+
+	RefParent::delete_by_rowid(bob).unwrap();
+	assert_eq!(select!(RefChild "WHERE rowid = ?", child_rowid).unwrap().sibling_of, None);
+
+	RefParent::delete_by_rowid(alice).unwrap();
+	assert_eq!(RefChild::count().unwrap(), 0);
+	assert_eq!(Ref::<RefParent>::new(alice).load().unwrap(), None);
+}
+
+#[derive(Turbosql, Default, Debug, PartialEq, Clone)]
+#[turbosql(check = "min_age <= max_age")]
+struct CheckTest {
+	rowid: Option<i64>,
+	#[turbosql(check = "min_age >= 0")]
+	min_age: Option<i64>,
+	max_age: Option<i64>,
+	label: Option<String>,
+}
+
+#[test]
+fn check_test() {
+	// this table's columns were added one by one, so the struct-level check is attached to the last column it mentions
+	assert_eq!(
+		select!(String "sql FROM sqlite_schema WHERE name = 'checktest'").unwrap(),
+		r#"CREATE TABLE checktest (rowid INTEGER PRIMARY KEY, min_age INTEGER CONSTRAINT "checktest: min_age >= 0" CHECK (min_age >= 0), max_age INTEGER CONSTRAINT "checktest: min_age <= max_age" CHECK (min_age <= max_age), label TEXT) STRICT"#
+	);
+
+	CheckTest { min_age: Some(1), max_age: Some(2), ..Default::default() }.insert().unwrap();
+
+	let e = CheckTest { min_age: Some(-1), ..Default::default() }.insert().unwrap_err();
+	assert!(
+		matches!(e, turbosql::Error::CheckViolation { ref table, ref constraint } if table == "checktest" && constraint == "min_age >= 0"),
+		"{:?}",
+		e
+	);
+
+	let e = execute!("UPDATE checktest SET max_age = 0").unwrap_err();
+	assert!(
+		matches!(e, turbosql::Error::CheckViolation { ref table, ref constraint } if table == "checktest" && constraint == "min_age <= max_age"),
+		"{:?}",
+		e
+	);
+	assert_eq!(e.to_string(), "CHECK constraint failed on table checktest: min_age <= max_age");
+
+	assert_eq!(CheckTest::count().unwrap(), 1);
+
+	// the same check on another table names that table
+	let e = CheckTwinTest { min_age: Some(-1), ..Default::default() }.insert().unwrap_err();
+	assert!(
+		matches!(e, turbosql::Error::CheckViolation { ref table, ref constraint } if table == "checktwintest" && constraint == "min_age >= 0"),
+		"{:?}",
+		e
+	);
+}
+
+#[derive(Turbosql, Default, Debug, PartialEq, Clone)]
+struct CheckTwinTest {
+	rowid: Option<i64>,
+	#[turbosql(check = "min_age >= 0")]
+	min_age: Option<i64>,
+}
+
+#[derive(Turbosql, Default, Debug, PartialEq, Clone)]
+#[turbosql(check = "lower <= upper OR note IS 'lower <= upper'")]
+struct TableCheckTest {
+	rowid: Option<i64>,
+	lower: Option<i64>,
+	upper: Option<i64>,
+	note: Option<String>,
+}
+
+#[test]
+fn table_check_test() {
+	// a new table is created with its struct-level checks as table constraints
+	assert_eq!(
+		select!(String "sql FROM sqlite_schema WHERE name = 'tablechecktest'").unwrap(),
+		r#"CREATE TABLE tablechecktest (rowid INTEGER PRIMARY KEY, lower INTEGER, upper INTEGER, note TEXT, CONSTRAINT "tablechecktest: lower <= upper OR note IS 'lower <= upper'" CHECK (lower <= upper OR note IS 'lower <= upper')) STRICT"#
+	);
+
+	let e =
+		TableCheckTest { lower: Some(2), upper: Some(1), ..Default::default() }.insert().unwrap_err();
+	assert!(
+		matches!(e, turbosql::Error::CheckViolation { ref table, .. } if table == "tablechecktest"),
+		"{:?}",
+		e
+	);
+	TableCheckTest { lower: Some(1), upper: Some(2), ..Default::default() }.insert().unwrap();
+}
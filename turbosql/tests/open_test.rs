@@ -0,0 +1,134 @@
+// cargo test --features test --manifest-path turbosql/Cargo.toml --test open_test
+
+#[cfg(not(feature = "test"))]
+compile_error!("turbosql must be tested with '--features test -- --test-threads=1'");
+#[cfg(not(test))]
+compile_error!("open_test.rs must be run in test mode");
+
+use turbosql::{rusqlite, select, Config, Error, SchemaCheck, Turbosql};
+
+#[derive(Turbosql, Default)]
+#[turbosql(db = "opentest")]
+struct OpenTestCache {
+	rowid: Option<i64>,
+	key: Option<String>,
+}
+
+#[test]
+fn open_test() {
+	let dir = std::env::temp_dir().join(format!("turbosql_open_test_{}", std::process::id()));
+	std::fs::create_dir_all(&dir).unwrap();
+
+	// missing file without create
+
+	Config::new().path(dir.join("missing.sqlite")).create(false).apply().unwrap();
+	assert!(matches!(turbosql::open(), Err(Error::OpenFailed { .. })));
+
+	// failures leave the database unopened, so the config can still be changed
+
+	let path = dir.join("open_test.sqlite");
+	Config::new().path(&path).schema_check(SchemaCheck::Error).apply().unwrap();
+
+	let conn = rusqlite::Connection::open(&path).unwrap();
+	conn
+		.execute_batch(
+			"CREATE TABLE _turbosql_migrations (rowid INTEGER PRIMARY KEY, migration TEXT NOT NULL) STRICT",
+		)
+		.unwrap();
+
+	// a newer binary has applied more migrations than this one knows
+
+	conn
+		.execute_batch(
+			"WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 10000)
+			INSERT INTO _turbosql_migrations(migration) SELECT 'x' || i FROM n",
+		)
+		.unwrap();
+	let Err(Error::DatabaseNewerThanBinary { applied, known, .. }) = turbosql::open() else {
+		panic!("expected DatabaseNewerThanBinary");
+	};
+	assert_eq!(applied, 10000);
+	assert!(known < applied);
+
+	// an applied migration differs from this binary's
+
+	conn.execute_batch("DELETE FROM _turbosql_migrations").unwrap();
+	conn
+		.execute("INSERT INTO _turbosql_migrations(migration) VALUES('CREATE TABLE bogus (x)')", [])
+		.unwrap();
+	let Err(Error::MigrationMismatch { applied, .. }) = turbosql::open() else {
+		panic!("expected MigrationMismatch");
+	};
+	assert_eq!(applied, "CREATE TABLE bogus (x)");
+
+	// failed migrations were rolled back
+
+	assert_eq!(
+		conn
+			.query_row("SELECT COUNT(*) FROM _turbosql_migrations", [], |row| row.get::<_, i64>(0))
+			.unwrap(),
+		1
+	);
+
+	// migrating would leave a foreign key pointing at a missing row
+
+	conn
+		.execute_batch(
+			"PRAGMA foreign_keys = OFF;
+			DELETE FROM _turbosql_migrations;
+			CREATE TABLE handref (x INTEGER REFERENCES personintegrationtest (rowid));
+			INSERT INTO handref VALUES (1);",
+		)
+		.unwrap();
+	let Err(Error::MigrationRejected { reason, .. }) = turbosql::open() else {
+		panic!("expected MigrationRejected");
+	};
+	assert_eq!(reason, "handref row 1 references a missing personintegrationtest row");
+
+	// recovered, and the migrated schema matches
+
+	conn.execute_batch("DROP TABLE handref").unwrap();
+	assert_eq!(
+		conn
+			.query_row("SELECT COUNT(*) FROM _turbosql_migrations", [], |row| row.get::<_, i64>(0))
+			.unwrap(),
+		0
+	);
+	turbosql::open().unwrap();
+	turbosql::open().unwrap();
+	assert_eq!(select!(i64 "COUNT(*) FROM personintegrationtest").unwrap(), 0);
+	assert!(Config::new().apply().is_err());
+
+	// a hand-edited database doesn't match the schema
+
+	let cache_path = dir.join("cache.sqlite");
+	let cache_conn = rusqlite::Connection::open(&cache_path).unwrap();
+	cache_conn
+		.execute_batch(
+			"CREATE TABLE _turbosql_migrations (rowid INTEGER PRIMARY KEY, migration TEXT NOT NULL) STRICT;
+			INSERT INTO _turbosql_migrations(migration) VALUES('CREATE TABLE opentestcache (rowid INTEGER PRIMARY KEY) STRICT');
+			CREATE TABLE opentestcache (rowid INTEGER PRIMARY KEY, extra BLOB) STRICT;
+			CREATE TABLE handmade (x);
+			CREATE INDEX handmade_index ON opentestcache (extra);
+			CREATE TRIGGER handmade_trigger AFTER INSERT ON opentestcache BEGIN INSERT INTO handmade VALUES (1); END;",
+		)
+		.unwrap();
+
+	// objects added by hand, which migrations.toml doesn't declare, aren't differences
+	Config::named("opentest").path(&cache_path).schema_check(SchemaCheck::Error).apply().unwrap();
+	let Err(Error::SchemaMismatch { differences, .. }) = turbosql::db("opentest").open() else {
+		panic!("expected SchemaMismatch");
+	};
+	assert_eq!(differences, vec!["table opentestcache: unexpected column extra".to_string()]);
+
+	// or just warn
+
+	Config::named("opentest").path(&cache_path).schema_check(SchemaCheck::Warn).apply().unwrap();
+	turbosql::db("opentest").open().unwrap();
+	OpenTestCache { rowid: None, key: Some("k".into()) }.insert().unwrap();
+	assert_eq!(OpenTestCache::count().unwrap(), 1);
+
+	drop(conn);
+	drop(cache_conn);
+	let _ = std::fs::remove_dir_all(&dir);
+}
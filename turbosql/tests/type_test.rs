@@ -0,0 +1,156 @@
+// cargo test --features test --manifest-path turbosql/Cargo.toml --test type_test -- --test-threads=1
+
+#[cfg(not(feature = "test"))]
+compile_error!("turbosql must be tested with '--features test -- --test-threads=1'");
+#[cfg(not(test))]
+compile_error!("type_test.rs must be run in test mode");
+
+use std::sync::Arc;
+use turbosql::{execute, select, FromSqlResult, ToSqlOutput, Turbosql, TurbosqlType, ValueRef};
+
+#[derive(TurbosqlType, Default, Debug, PartialEq, Clone)]
+struct Email(String);
+
+#[derive(TurbosqlType, Default, Debug, PartialEq, Clone)]
+struct Score {
+	points: i64,
+}
+
+/// Stored as `#rrggbb` text by hand.
+#[derive(Default, Debug, PartialEq, Clone, Copy)]
+struct Rgb(u8, u8, u8);
+
+impl TurbosqlType for Rgb {
+	const SQL_TYPE: &'static str = "TEXT";
+	fn to_sql_output(&self) -> turbosql::rusqlite::Result<ToSqlOutput<'_>> {
+		Ok(ToSqlOutput::from(format!("#{:02x}{:02x}{:02x}", self.0, self.1, self.2)))
+	}
+	fn from_sql_value(value: ValueRef<'_>) -> FromSqlResult<Self> {
+		let hex = u32::from_str_radix(value.as_str()?.trim_start_matches('#'), 16)
+			.map_err(|e| turbosql::rusqlite::types::FromSqlError::Other(e.into()))?;
+		Ok(Rgb((hex >> 16) as u8, (hex >> 8) as u8, hex as u8))
+	}
+}
+
+type Name = String;
+
+#[derive(Turbosql, Default, Debug, PartialEq, Clone)]
+struct TypeTest {
+	rowid: Option<i64>,
+	#[turbosql(sql_type = "TEXT")]
+	email: Option<Email>,
+	#[turbosql(sql_type = "INTEGER")]
+	score: Score,
+	#[turbosql(sql_type = "TEXT")]
+	color: Option<Rgb>,
+	#[turbosql(sql_type = "TEXT")]
+	name: Option<Name>,
+	qualified: Option<std::string::String>,
+	#[turbosql(sql_type = "TEXT")]
+	boxed: Option<Box<str>>,
+	#[turbosql(sql_type = "TEXT")]
+	shared: Option<Arc<str>>,
+}
+
+/// Its table is in `test.migrations.toml` as an earlier version wrote it, when every `TEXT` column but `String` was JSON.
+#[derive(Turbosql, Default, Debug, PartialEq, Clone)]
+#[turbosql(db = "legacy")]
+struct LegacyText {
+	rowid: Option<i64>,
+	qualified: Option<std::string::String>,
+	#[turbosql(sql_type = "TEXT")]
+	boxed: Option<Box<str>>,
+	#[turbosql(sql_type = "TEXT")]
+	name: Option<Name>,
+	tags: Option<Vec<String>>,
+}
+
+#[test]
+fn type_test() {
+	execute!("DELETE FROM typetest").unwrap();
+
+	let row = TypeTest {
+		rowid: None,
+		email: Some(Email("a@example.com".into())),
+		score: Score { points: 42 },
+		color: Some(Rgb(255, 128, 0)),
+		name: Some("Ada".into()),
+		qualified: Some("qualified".into()),
+		boxed: Some("boxed".into()),
+		shared: Some("shared".into()),
+	};
+	let rowid = row.insert().unwrap();
+	let row = TypeTest { rowid: Some(rowid), ..row };
+
+	assert_eq!(select!(TypeTest "WHERE rowid = ?", rowid).unwrap(), row);
+	assert_eq!(TypeTest::get(rowid).unwrap(), Some(row.clone()));
+
+	// stored natively rather than as JSON
+	assert_eq!(select!(String "email FROM typetest").unwrap(), "a@example.com");
+	assert_eq!(select!(i64 "score FROM typetest").unwrap(), 42);
+	assert_eq!(select!(String "color FROM typetest").unwrap(), "#ff8000");
+	assert_eq!(select!(String "name FROM typetest").unwrap(), "Ada");
+	assert_eq!(select!(String "qualified FROM typetest").unwrap(), "qualified");
+	assert_eq!(select!(String "boxed FROM typetest").unwrap(), "boxed");
+	assert_eq!(select!(String "shared FROM typetest").unwrap(), "shared");
+
+	// derived types bind as parameters
+	let email = Email("a@example.com".into());
+	assert_eq!(select!(i64 "COUNT(*) FROM typetest WHERE email = ?", email).unwrap(), 1);
+	assert_eq!(select!(Vec<TypeTest.score>).unwrap(), vec![Score { points: 42 }]);
+
+	// update and NULL
+	let row = TypeTest { email: None, color: None, score: Score { points: -1 }, ..row };
+	row.update().unwrap();
+	assert_eq!(TypeTest::get(rowid).unwrap(), Some(row));
+	assert_eq!(select!(String "typeof(email) FROM typetest").unwrap(), "null");
+}
+
+#[test]
+fn legacy_text_test() {
+	let path =
+		std::env::temp_dir().join(format!("turbosql_legacy_text_test_{}.sqlite", std::process::id()));
+	let _ = std::fs::remove_file(&path);
+
+	// a database written before its JSON columns were converted
+	let conn = turbosql::rusqlite::Connection::open(&path).unwrap();
+	conn
+		.execute_batch(
+			r#"CREATE TABLE _turbosql_migrations (rowid INTEGER PRIMARY KEY, migration TEXT NOT NULL) STRICT;
+			INSERT INTO _turbosql_migrations(migration) VALUES
+				('CREATE TABLE legacytext (rowid INTEGER PRIMARY KEY) STRICT'),
+				('ALTER TABLE legacytext ADD COLUMN qualified TEXT'),
+				('ALTER TABLE legacytext ADD COLUMN boxed TEXT'),
+				('ALTER TABLE legacytext ADD COLUMN name TEXT'),
+				('ALTER TABLE legacytext ADD COLUMN tags TEXT');
+			CREATE TABLE legacytext (rowid INTEGER PRIMARY KEY, qualified TEXT, boxed TEXT, name TEXT, tags TEXT) STRICT;
+			INSERT INTO legacytext (qualified, boxed, name, tags) VALUES ('"q"', '"b"', '"n"', '["t"]'), ('null', 'null', 'null', 'null');"#,
+		)
+		.unwrap();
+	drop(conn);
+
+	turbosql::Config::named("legacy").path(&path).apply().unwrap();
+
+	assert_eq!(
+		LegacyText::all().unwrap(),
+		[
+			LegacyText {
+				rowid: Some(1),
+				qualified: Some("q".into()),
+				boxed: Some("b".into()),
+				name: Some("n".into()),
+				tags: Some(vec!["t".into()]),
+			},
+			LegacyText { rowid: Some(2), ..Default::default() },
+		]
+	);
+	assert_eq!(
+		select!(db = "legacy", (String, String, String, String) "qualified, boxed, name, tags FROM legacytext WHERE rowid = 1")
+			.unwrap(),
+		("q".into(), "b".into(), "n".into(), "[\"t\"]".into())
+	);
+
+	for ext in ["sqlite", "sqlite-wal", "sqlite-shm"] {
+		let _ = std::fs::remove_file(path.with_extension(ext));
+	}
+}
@@ -26,9 +26,18 @@ struct Person {
 	age: Option<u8>,
 }
 
+#[derive(Turbosql, Default)]
+#[turbosql(db = "ui")]
+struct UiCache {
+	rowid: Option<i64>,
+	key: Option<String>,
+}
+
 fn main() {
 	select!(Person "WHERE age = " 24 " AND name = ?", "Bob").unwrap();
 	select!(Person "WHERE age = " 24 " AND name = $name").unwrap();
+	select!(Person "WHERE age = $1age").unwrap();
+	select!(Person "WHERE age = $age AND name = ?").unwrap();
 	select!(Person "WHERE age = ?", 1, 2).unwrap();
 	select!(Person "WHERE age = ").unwrap();
 	select!("UPDATE person SET age = 1").unwrap();
@@ -37,4 +46,11 @@ fn main() {
 	select!(Nonexistenttable).unwrap();
 	select!(Vec).unwrap();
 	select!(Vec<"what">).unwrap();
+	select!((String, i64) "name FROM person").unwrap();
+	select!((String, i64) "name, age FROM nonexistenttable").unwrap();
+	select!(Person.nonexistentcolumn).unwrap();
+	select!(Vec<Nonexistenttable.name>).unwrap();
+	select!(Vec<UiCache>).unwrap();
+	select!(db = "bad name", Vec<UiCache>).unwrap();
+	select!(db = "ui", Vec<Person>).unwrap();
 }
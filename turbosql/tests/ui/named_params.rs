@@ -0,0 +1,15 @@
+// TRYBUILD=overwrite cargo test -p turbosql --test ui
+
+use turbosql::{select, Turbosql};
+
+#[derive(Turbosql, Default)]
+struct NamedParams {
+	rowid: Option<i64>,
+	name: Option<String>,
+	age: Option<u8>,
+}
+
+fn main() {
+	let age = 24;
+	select!(NamedParams "WHERE age = $age AND name = $missing").unwrap();
+}
@@ -0,0 +1,82 @@
+// cargo test --features "test uuid url" --manifest-path turbosql/Cargo.toml --test uuid_test -- --test-threads=1
+
+#[cfg(not(feature = "test"))]
+compile_error!("turbosql must be tested with '--features test -- --test-threads=1'");
+#[cfg(not(test))]
+compile_error!("uuid_test.rs must be run in test mode");
+
+use turbosql::{execute, select, Blob, Turbosql, UuidText};
+use url::Url;
+use uuid::Uuid;
+
+#[derive(Turbosql, Default, Debug, PartialEq, Clone)]
+struct UuidTest {
+	rowid: Option<i64>,
+	#[turbosql(uuid_v7)]
+	id: Option<Uuid>,
+	#[turbosql(uuid_as = "blob")]
+	blob: Option<Uuid>,
+	#[turbosql(uuid_as = "text")]
+	text: Option<Uuid>,
+	#[turbosql(uuid_as = "text")]
+	required_text: Uuid,
+	#[turbosql(url_as = "text")]
+	homepage: Option<Url>,
+	json: Option<Uuid>,
+	homepage_json: Option<Url>,
+	name: Option<String>,
+}
+
+#[test]
+fn uuid_test() {
+	execute!("DELETE FROM uuidtest").unwrap();
+
+	let uuid = Uuid::from_u128(0x0123_4567_89ab_cdef_0123_4567_89ab_cdef);
+	let homepage = Url::parse("https://example.com/a?b=c").unwrap();
+
+	let mut row = UuidTest {
+		rowid: None,
+		id: None,
+		blob: Some(uuid),
+		text: Some(uuid),
+		required_text: uuid,
+		homepage: Some(homepage.clone()),
+		json: Some(uuid),
+		homepage_json: Some(homepage.clone()),
+		name: Some("first".into()),
+	};
+	let rowid = row.insert_mut().unwrap();
+	let id = row.id.unwrap();
+	assert_eq!(id.get_version_num(), 7);
+	assert_eq!(UuidTest::get(rowid).unwrap(), Some(row.clone()));
+
+	// storage
+	assert_eq!(select!(Blob "blob FROM uuidtest").unwrap(), uuid.as_bytes());
+	assert_eq!(select!(String "text FROM uuidtest").unwrap(), "01234567-89ab-cdef-0123-456789abcdef");
+	assert_eq!(select!(String "homepage FROM uuidtest").unwrap(), "https://example.com/a?b=c");
+	// fields without uuid_as or url_as stay JSON, as they were stored before native columns
+	assert_eq!(
+		select!(String "json FROM uuidtest").unwrap(),
+		"\"01234567-89ab-cdef-0123-456789abcdef\""
+	);
+	assert_eq!(
+		select!(String "homepage_json FROM uuidtest").unwrap(),
+		"\"https://example.com/a?b=c\""
+	);
+
+	// single columns and bound parameters
+	assert_eq!(select!(Uuid "blob FROM uuidtest WHERE id = ?", id).unwrap(), uuid);
+	assert_eq!(select!(UuidTest.text).unwrap(), Some(uuid));
+	assert_eq!(select!(UuidText<Uuid> "text FROM uuidtest").unwrap(), UuidText(uuid));
+	assert_eq!(select!(i64 "rowid FROM uuidtest WHERE text = ?", UuidText(uuid)).unwrap(), rowid);
+	assert_eq!(select!(Url "homepage FROM uuidtest WHERE homepage = ?", homepage).unwrap(), homepage);
+
+	// insert fills in a new UUIDv7 that sorts after earlier ones, and upsert_on_id overwrites by id
+	UuidTest { name: Some("second".into()), ..Default::default() }.insert().unwrap();
+	assert_eq!(select!(Vec<String> "name FROM uuidtest ORDER BY id").unwrap(), ["first", "second"]);
+	assert!(select!(Uuid "id FROM uuidtest WHERE name = 'second'").unwrap() > id);
+
+	UuidTest { name: Some("renamed".into()), ..row.clone() }.upsert_on_id().unwrap();
+	assert_eq!(select!(String "name FROM uuidtest WHERE rowid = ?", rowid).unwrap(), "renamed");
+	assert_eq!(select!(i64 "COUNT(*) FROM uuidtest").unwrap(), 2);
+}